@@ -0,0 +1,729 @@
+use clap::{Parser, ValueEnum};
+use dither_core::{
+    Algorithm, Binarization, BitOrder, ChannelDecorrelation, ColorStop, Crop, Denoise, DitherMode,
+    DitherSettings, DitherSpace, EdgeMode, Equalization, EscPosMode, EscPosOptions, Layout,
+    LumaModel, PRINTER_58MM, PRINTER_80MM, PackOptions, Planes, PreviewFormat, Recipe,
+    ResizeFilter, ThresholdMap,
+};
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "dither-cli",
+    version,
+    about = "Batch dithering from the command line"
+)]
+pub struct Args {
+    /// Input files, directories or glob patterns; `-` reads from stdin.
+    #[arg(required = true)]
+    pub inputs: Vec<String>,
+
+    /// Output directory; `-` writes to stdout. Defaults to the input's directory.
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Output file name template: {stem}, {name}, {ext}, {algorithm}, {mode}, {index}
+    /// ({index} is not available with --watch).
+    #[arg(long, default_value = "{stem}_{algorithm}.{ext}")]
+    pub name: String,
+
+    /// Output format (file extension). Packed framebuffers: `bin` (raw bytes), `h` (C header),
+    /// `rs` (Rust), `py` (MicroPython) and `xbm` (Adafruit GFX / u8g2); `escpos` for receipt
+    /// printers. PNG, JPEG and GIF output carries the settings as a recipe in its metadata;
+    /// other image formats such as WebP and BMP are written without it.
+    #[arg(short, long, default_value = "png")]
+    pub format: String,
+
+    /// Search directories recursively. Needs `-o`, so earlier outputs are not picked up
+    /// as inputs.
+    #[arg(short, long)]
+    pub recursive: bool,
+
+    /// Number of worker threads (0 = all cores).
+    #[arg(short, long, default_value_t = 0)]
+    pub jobs: usize,
+
+    /// Keep watching the inputs and process new or changed images.
+    #[arg(short, long)]
+    pub watch: bool,
+
+    /// Load all processing settings from a TOML/JSON recipe; processing flags are
+    /// ignored, --width/--height still override the recipe size and --crop still applies.
+    #[arg(long)]
+    pub recipe: Option<PathBuf>,
+
+    /// Write the effective settings to a recipe file.
+    #[arg(long)]
+    pub save_recipe: Option<PathBuf>,
+
+    /// Send output to a file or device such as /dev/usb/lp0 instead of -o. Data is appended
+    /// and inputs are processed in order.
+    #[arg(long, conflicts_with_all = ["output", "watch"])]
+    pub device: Option<PathBuf>,
+
+    /// Show each result in the terminal: `sixel` graphics, `ansi` 24-bit half blocks or
+    /// `braille` (1-bit images only). Without -o or --device nothing is written.
+    #[arg(long, value_enum)]
+    pub preview: Option<PreviewArg>,
+
+    /// Terminal columns for ansi and braille previews; defaults to $COLUMNS or 80.
+    #[arg(long)]
+    pub preview_width: Option<u32>,
+
+    /// Polling interval in seconds for --watch.
+    #[arg(long, default_value_t = 2.0)]
+    pub interval: f32,
+
+    #[command(flatten)]
+    pub dither: DitherArgs,
+
+    #[command(flatten)]
+    pub color: ColorArgs,
+
+    #[command(flatten)]
+    pub adjust: AdjustArgs,
+
+    #[command(flatten)]
+    pub resize: ResizeArgs,
+
+    #[command(flatten)]
+    pub pack: PackArgs,
+
+    #[command(flatten)]
+    pub printer: PrinterArgs,
+}
+
+#[derive(clap::Args, Debug)]
+#[command(next_help_heading = "Algorithm")]
+pub struct DitherArgs {
+    #[arg(short, long, value_enum, default_value_t = AlgorithmArg::Bayer)]
+    pub algorithm: AlgorithmArg,
+
+    /// Bayer matrix size as a power of two (1..=6).
+    #[arg(long, default_value_t = 2, value_parser = clap::value_parser!(u8).range(1..=6))]
+    pub bayer_size: u8,
+
+    /// Seed for noise thresholds and diffusion noise.
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+
+    /// Fraction of the error that is diffused (0..=1).
+    #[arg(long, default_value_t = 1.0)]
+    pub diffusion: f32,
+
+    /// Threshold bias for diffusion (-127..=127).
+    #[arg(long, default_value_t = 0, allow_hyphen_values = true)]
+    pub bias: i16,
+
+    /// Threshold noise amplitude for diffusion (0..=127).
+    #[arg(long, default_value_t = 0)]
+    pub noise: u8,
+
+    /// Hybrid blend: 0 = ordered, 1 = diffusion.
+    #[arg(long, default_value_t = 0.5)]
+    pub blend: f32,
+
+    /// Threshold map file (image or text) for `custom-map` and `hybrid`.
+    #[arg(long)]
+    pub map: Option<PathBuf>,
+
+    #[arg(long, value_enum, default_value_t = BinarizeArg::Otsu)]
+    pub binarize: BinarizeArg,
+
+    /// Window radius for local binarization.
+    #[arg(long, default_value_t = 15)]
+    pub window: u32,
+
+    /// k for Sauvola/Niblack (defaults 0.34 / -0.2), sensitivity for Bradley (0.15).
+    #[arg(long, allow_hyphen_values = true)]
+    pub k: Option<f32>,
+
+    #[arg(long, value_enum, default_value_t = EdgeArg::Off)]
+    pub edges: EdgeArg,
+
+    #[arg(long, default_value_t = 64)]
+    pub edge_threshold: u8,
+
+    #[arg(long, default_value_t = 2.0)]
+    pub edge_strength: f32,
+}
+
+#[derive(clap::Args, Debug)]
+#[command(next_help_heading = "Color")]
+pub struct ColorArgs {
+    #[arg(short, long, value_enum, default_value_t = ModeArg::Gray)]
+    pub mode: ModeArg,
+
+    /// Palette stop `POSITION:RRGGBB` for tones mode, repeatable.
+    #[arg(long = "stop", value_parser = parse_stop)]
+    pub stops: Vec<ColorStop>,
+
+    /// Luma model: rec601, rec709, linear, red, green, blue or `R,G,B` weights.
+    #[arg(long, default_value = "rec601", value_parser = parse_luma)]
+    pub luma: LumaModel,
+
+    #[arg(long, value_enum, default_value_t = SpaceArg::Rgb)]
+    pub space: SpaceArg,
+
+    #[arg(long, value_enum, default_value_t = ChannelsArg::Shared)]
+    pub channels: ChannelsArg,
+}
+
+#[derive(clap::Args, Debug)]
+#[command(next_help_heading = "Adjustments")]
+pub struct AdjustArgs {
+    #[arg(long, default_value_t = 0.0, allow_hyphen_values = true)]
+    pub brightness: f32,
+
+    #[arg(long, default_value_t = 0.0, allow_hyphen_values = true)]
+    pub contrast: f32,
+
+    #[arg(long, default_value_t = 1.0)]
+    pub gamma: f32,
+
+    #[arg(long, default_value_t = 0)]
+    pub black: u8,
+
+    #[arg(long, default_value_t = 255)]
+    pub white: u8,
+
+    /// Tone curve points `IN:OUT,IN:OUT,...`.
+    #[arg(long, value_delimiter = ',', value_parser = parse_curve_point)]
+    pub curve: Vec<(u8, u8)>,
+
+    #[arg(long, value_enum, default_value_t = EqualizeArg::None)]
+    pub equalize: EqualizeArg,
+
+    #[arg(long, default_value_t = 8)]
+    pub clahe_tiles: u32,
+
+    #[arg(long, default_value_t = 2.0)]
+    pub clahe_clip: f32,
+
+    /// Percent of pixels clipped on each side by auto-levels.
+    #[arg(long, default_value_t = 0.5)]
+    pub levels_clip: f32,
+
+    #[arg(long, value_enum, default_value_t = DenoiseArg::None)]
+    pub denoise: DenoiseArg,
+
+    #[arg(long, default_value_t = 1)]
+    pub denoise_radius: u32,
+
+    /// Color similarity sigma for bilateral denoise.
+    #[arg(long, default_value_t = 25.0)]
+    pub denoise_strength: f32,
+
+    #[arg(long, default_value_t = 0.0)]
+    pub sharpen: f32,
+
+    #[arg(long, default_value_t = 1.0)]
+    pub sharpen_radius: f32,
+
+    #[arg(long, default_value_t = 0)]
+    pub sharpen_threshold: u8,
+}
+
+#[derive(clap::Args, Debug)]
+#[command(next_help_heading = "Resize")]
+pub struct ResizeArgs {
+    /// Crop rectangle `X,Y,WIDTH,HEIGHT` applied before resizing. Also applies with
+    /// --recipe and is never stored in recipes.
+    #[arg(long, value_parser = parse_crop)]
+    pub crop: Option<Crop>,
+
+    /// Target width; with only one dimension set the aspect ratio is kept.
+    #[arg(long)]
+    pub width: Option<u32>,
+
+    #[arg(long)]
+    pub height: Option<u32>,
+
+    #[arg(long, value_enum, default_value_t = FilterArg::Nearest)]
+    pub filter: FilterArg,
+
+    /// Resample in linear light.
+    #[arg(long)]
+    pub gamma_correct: bool,
+
+    /// Dither at 1/N resolution and upscale with nearest neighbour.
+    #[arg(long, default_value_t = 1)]
+    pub pixel_scale: u32,
+}
+
+#[derive(clap::Args, Debug)]
+#[command(next_help_heading = "Framebuffer (-f bin, h, rs, py, xbm)")]
+pub struct PackArgs {
+    /// Bits per pixel: 1, 2, 4 or 8.
+    #[arg(long, default_value_t = 1)]
+    pub bpp: u8,
+
+    /// Bit that holds the first (leftmost or topmost) pixel of a byte.
+    #[arg(long, value_enum, default_value_t = BitOrderArg::Msb)]
+    pub bit_order: BitOrderArg,
+
+    /// `pages` stores vertical strips per byte, as SSD1306 expects (use with --bit-order lsb).
+    #[arg(long, value_enum, default_value_t = LayoutArg::Rows)]
+    pub layout: LayoutArg,
+
+    /// Pad every row to a multiple of N bytes.
+    #[arg(long, default_value_t = 1)]
+    pub row_align: usize,
+
+    /// Invert all bits.
+    #[arg(long)]
+    pub invert: bool,
+
+    /// `black-red` writes a black plane followed by a red plane for tri-color e-paper.
+    #[arg(long, value_enum, default_value_t = PlanesArg::Single)]
+    pub planes: PlanesArg,
+}
+
+impl PackArgs {
+    pub fn options(&self) -> PackOptions {
+        PackOptions {
+            bits: self.bpp,
+            bit_order: match self.bit_order {
+                BitOrderArg::Msb => BitOrder::MsbFirst,
+                BitOrderArg::Lsb => BitOrder::LsbFirst,
+            },
+            layout: match self.layout {
+                LayoutArg::Rows => Layout::Rows,
+                LayoutArg::Pages => Layout::Pages,
+            },
+            row_align: self.row_align.max(1),
+            invert: self.invert,
+            planes: match self.planes {
+                PlanesArg::Single => Planes::Single,
+                PlanesArg::BlackRed => Planes::BlackRed,
+            },
+        }
+    }
+}
+
+#[derive(clap::Args, Debug)]
+#[command(next_help_heading = "Receipt printer (-f escpos)")]
+pub struct PrinterArgs {
+    /// Print head width in dots: a number, `58mm` (384) or `80mm` (576). Images are resized
+    /// to this width unless a smaller --width is given.
+    #[arg(long, default_value = "58mm", value_parser = parse_printer_width)]
+    pub printer_width: u32,
+
+    /// Use column mode `ESC *` for printers without `GS v 0` raster support.
+    #[arg(long)]
+    pub column_mode: bool,
+
+    /// Blank lines fed after the image.
+    #[arg(long, default_value_t = 3)]
+    pub feed: u8,
+
+    /// Cut the paper after printing.
+    #[arg(long)]
+    pub cut: bool,
+}
+
+impl PrinterArgs {
+    pub fn options(&self) -> EscPosOptions {
+        EscPosOptions {
+            mode: if self.column_mode {
+                EscPosMode::Column
+            } else {
+                EscPosMode::Raster
+            },
+            width: self.printer_width,
+            feed: self.feed,
+            cut: self.cut,
+        }
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum AlgorithmArg {
+    Original,
+    Bayer,
+    WhiteNoise,
+    TriangularNoise,
+    #[value(alias = "ign")]
+    InterleavedGradient,
+    CustomMap,
+    Floyd,
+    Hybrid,
+    Binarize,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum ModeArg {
+    Gray,
+    Rgb,
+    Tones,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum BinarizeArg {
+    Otsu,
+    Sauvola,
+    Niblack,
+    Bradley,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum EdgeArg {
+    Off,
+    Modulate,
+    Hard,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum SpaceArg {
+    Rgb,
+    Ycbcr,
+    Oklab,
+    Cmy,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum ChannelsArg {
+    Shared,
+    Offset,
+    Rotate,
+    Transpose,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum EqualizeArg {
+    None,
+    Global,
+    Clahe,
+    AutoLevels,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum DenoiseArg {
+    None,
+    Median,
+    Bilateral,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum FilterArg {
+    Nearest,
+    Triangle,
+    CatmullRom,
+    Gaussian,
+    Lanczos3,
+    Area,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum BitOrderArg {
+    Msb,
+    Lsb,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum LayoutArg {
+    Rows,
+    Pages,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum PlanesArg {
+    Single,
+    BlackRed,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum PreviewArg {
+    Sixel,
+    Ansi,
+    Braille,
+}
+
+fn parse_color(s: &str) -> Result<[u8; 3], String> {
+    let hex = s.trim_start_matches('#');
+    if hex.len() != 6 {
+        return Err(format!("expected RRGGBB, got `{s}`"));
+    }
+    let channel = |i: usize| {
+        u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| format!("invalid color `{s}`"))
+    };
+    Ok([channel(0)?, channel(2)?, channel(4)?])
+}
+
+fn parse_stop(s: &str) -> Result<ColorStop, String> {
+    let (position, color) = s
+        .split_once(':')
+        .ok_or_else(|| format!("expected POSITION:RRGGBB, got `{s}`"))?;
+    let position = position
+        .parse()
+        .map_err(|_| format!("invalid stop position `{position}`"))?;
+    Ok(ColorStop::new(position, parse_color(color)?))
+}
+
+fn parse_luma(s: &str) -> Result<LumaModel, String> {
+    Ok(match s.to_ascii_lowercase().as_str() {
+        "rec601" => LumaModel::Rec601,
+        "rec709" => LumaModel::Rec709,
+        "linear" => LumaModel::LinearLight,
+        "red" => LumaModel::Red,
+        "green" => LumaModel::Green,
+        "blue" => LumaModel::Blue,
+        weights => {
+            let values: Vec<f32> = weights
+                .split(',')
+                .map(|w| w.trim().parse::<f32>())
+                .collect::<Result<_, _>>()
+                .map_err(|_| format!("unknown luma model `{s}`"))?;
+            let [r, g, b] = values[..] else {
+                return Err(format!("custom luma needs three weights, got `{s}`"));
+            };
+            LumaModel::Custom([r, g, b])
+        }
+    })
+}
+
+fn parse_printer_width(s: &str) -> Result<u32, String> {
+    match s.to_ascii_lowercase().as_str() {
+        "58mm" => Ok(PRINTER_58MM),
+        "80mm" => Ok(PRINTER_80MM),
+        dots => match dots.parse::<u32>() {
+            Ok(width) if (8..=u16::MAX as u32).contains(&width) => Ok(width),
+            _ => Err(format!("expected 58mm, 80mm or a width in dots, got `{s}`")),
+        },
+    }
+}
+
+fn parse_crop(s: &str) -> Result<Crop, String> {
+    let values: Vec<u32> = s
+        .split(',')
+        .map(|v| v.trim().parse::<u32>())
+        .collect::<Result<_, _>>()
+        .map_err(|_| format!("invalid crop `{s}`"))?;
+    let [x, y, width, height] = values[..] else {
+        return Err(format!("expected X,Y,WIDTH,HEIGHT, got `{s}`"));
+    };
+    if width == 0 || height == 0 {
+        return Err(format!("empty crop `{s}`"));
+    }
+    Ok(Crop {
+        x,
+        y,
+        width,
+        height,
+    })
+}
+
+fn parse_curve_point(s: &str) -> Result<(u8, u8), String> {
+    let (input, output) = s
+        .split_once(':')
+        .ok_or_else(|| format!("expected IN:OUT, got `{s}`"))?;
+    let value = |v: &str| {
+        v.trim()
+            .parse::<u8>()
+            .map_err(|_| format!("invalid curve value `{v}`"))
+    };
+    Ok((value(input)?, value(output)?))
+}
+
+impl AlgorithmArg {
+    /// Nazwa flagi dla algorytmu z ustawien (moga pochodzic z przepisu).
+    pub fn name_of(algorithm: Algorithm) -> String {
+        let arg = match algorithm {
+            Algorithm::Original => Self::Original,
+            Algorithm::Bayer => Self::Bayer,
+            Algorithm::WhiteNoise => Self::WhiteNoise,
+            Algorithm::TriangularNoise => Self::TriangularNoise,
+            Algorithm::InterleavedGradient => Self::InterleavedGradient,
+            Algorithm::CustomMap => Self::CustomMap,
+            Algorithm::Floyd => Self::Floyd,
+            Algorithm::Hybrid => Self::Hybrid,
+            Algorithm::Binarize => Self::Binarize,
+        };
+        arg.to_possible_value().unwrap().get_name().to_string()
+    }
+}
+
+impl ModeArg {
+    pub fn name_of(mode: DitherMode) -> String {
+        let arg = match mode {
+            DitherMode::Grayscale => Self::Gray,
+            DitherMode::Colored => Self::Rgb,
+            DitherMode::Duoton => Self::Tones,
+        };
+        arg.to_possible_value().unwrap().get_name().to_string()
+    }
+}
+
+impl Args {
+    /// Ustawienia wspolne dla wszystkich plikow; rozmiar docelowy uzupelnia `target_size`.
+    pub fn settings(&self) -> Result<DitherSettings, String> {
+        if let Some(path) = &self.recipe {
+            // Przepis nie zawiera wycinka, --crop dziala tak jak --width i --height
+            return Recipe::load(path)
+                .map(|recipe| DitherSettings {
+                    crop: self.resize.crop,
+                    ..recipe.settings
+                })
+                .map_err(|e| format!("{}: {e}", path.display()));
+        }
+
+        let d = &self.dither;
+        let c = &self.color;
+        let a = &self.adjust;
+        let r = &self.resize;
+
+        let mut settings = DitherSettings {
+            algorithm: match d.algorithm {
+                AlgorithmArg::Original => Algorithm::Original,
+                AlgorithmArg::Bayer => Algorithm::Bayer,
+                AlgorithmArg::WhiteNoise => Algorithm::WhiteNoise,
+                AlgorithmArg::TriangularNoise => Algorithm::TriangularNoise,
+                AlgorithmArg::InterleavedGradient => Algorithm::InterleavedGradient,
+                AlgorithmArg::CustomMap => Algorithm::CustomMap,
+                AlgorithmArg::Floyd => Algorithm::Floyd,
+                AlgorithmArg::Hybrid => Algorithm::Hybrid,
+                AlgorithmArg::Binarize => Algorithm::Binarize,
+            },
+            mode: match c.mode {
+                ModeArg::Gray => DitherMode::Grayscale,
+                ModeArg::Rgb => DitherMode::Colored,
+                ModeArg::Tones => DitherMode::Duoton,
+            },
+            bayer_size: d.bayer_size as usize,
+            noise_seed: d.seed,
+            hybrid_blend: d.blend,
+            binarization: match d.binarize {
+                BinarizeArg::Otsu => Binarization::Otsu,
+                BinarizeArg::Sauvola => Binarization::Sauvola {
+                    radius: d.window,
+                    k: d.k.unwrap_or(0.34),
+                },
+                BinarizeArg::Niblack => Binarization::Niblack {
+                    radius: d.window,
+                    k: d.k.unwrap_or(-0.2),
+                },
+                BinarizeArg::Bradley => Binarization::Bradley {
+                    radius: d.window,
+                    t: d.k.unwrap_or(0.15),
+                },
+            },
+            luma_model: c.luma,
+            channel_decorrelation: match c.channels {
+                ChannelsArg::Shared => ChannelDecorrelation::None,
+                ChannelsArg::Offset => ChannelDecorrelation::Offset,
+                ChannelsArg::Rotate => ChannelDecorrelation::Rotate,
+                ChannelsArg::Transpose => ChannelDecorrelation::Transpose,
+            },
+            dither_space: match c.space {
+                SpaceArg::Rgb => DitherSpace::Rgb,
+                SpaceArg::Ycbcr => DitherSpace::YCbCr,
+                SpaceArg::Oklab => DitherSpace::OkLab,
+                SpaceArg::Cmy => DitherSpace::Cmy,
+            },
+            resize_filter: match r.filter {
+                FilterArg::Nearest => ResizeFilter::Nearest,
+                FilterArg::Triangle => ResizeFilter::Triangle,
+                FilterArg::CatmullRom => ResizeFilter::CatmullRom,
+                FilterArg::Gaussian => ResizeFilter::Gaussian,
+                FilterArg::Lanczos3 => ResizeFilter::Lanczos3,
+                FilterArg::Area => ResizeFilter::Area,
+            },
+            crop: r.crop,
+            gamma_correct_resize: r.gamma_correct,
+            pixel_scale: r.pixel_scale.max(1),
+            ..DitherSettings::default()
+        };
+
+        settings.diffusion.strength = d.diffusion.clamp(0.0, 1.0);
+        settings.diffusion.bias = d.bias.clamp(-127, 127);
+        settings.diffusion.noise = d.noise.min(127);
+
+        if let Some(path) = &d.map {
+            let map = ThresholdMap::load(path).map_err(|e| format!("{}: {e}", path.display()))?;
+            settings.custom_map = Some(map);
+            settings.hybrid_custom_map = true;
+        } else if d.algorithm == AlgorithmArg::CustomMap {
+            return Err("`custom-map` needs --map".to_string());
+        }
+
+        if !c.stops.is_empty() {
+            let mut stops = c.stops.clone();
+            stops.sort_by_key(|s| s.position);
+            settings.color_stops = stops;
+        }
+
+        settings.edge.mode = match d.edges {
+            EdgeArg::Off => EdgeMode::Off,
+            EdgeArg::Modulate => EdgeMode::Modulate,
+            EdgeArg::Hard => EdgeMode::Hard,
+        };
+        settings.edge.threshold = d.edge_threshold;
+        settings.edge.strength = d.edge_strength;
+
+        let adj = &mut settings.adjustments;
+        adj.brightness = a.brightness;
+        adj.contrast = a.contrast;
+        adj.gamma = a.gamma;
+        adj.black_point = a.black;
+        adj.white_point = a.white;
+        adj.curve = a.curve.clone();
+        adj.equalization = match a.equalize {
+            EqualizeArg::None => Equalization::None,
+            EqualizeArg::Global => Equalization::Global,
+            EqualizeArg::Clahe => Equalization::Clahe {
+                tiles: a.clahe_tiles,
+                clip_limit: a.clahe_clip,
+            },
+            EqualizeArg::AutoLevels => Equalization::AutoLevels {
+                clip: a.levels_clip,
+            },
+        };
+        adj.denoise = match a.denoise {
+            DenoiseArg::None => Denoise::None,
+            DenoiseArg::Median => Denoise::Median {
+                radius: a.denoise_radius,
+            },
+            DenoiseArg::Bilateral => Denoise::Bilateral {
+                radius: a.denoise_radius,
+                sigma_color: a.denoise_strength,
+            },
+        };
+        adj.sharpen.amount = a.sharpen;
+        adj.sharpen.radius = a.sharpen_radius;
+        adj.sharpen.threshold = a.sharpen_threshold;
+
+        Ok(settings)
+    }
+
+    /// Format podgladu i szerokosc w kolumnach terminala.
+    pub fn preview(&self) -> Option<(PreviewFormat, u32)> {
+        let format = match self.preview? {
+            PreviewArg::Sixel => PreviewFormat::Sixel,
+            PreviewArg::Ansi => PreviewFormat::Ansi,
+            PreviewArg::Braille => PreviewFormat::Braille,
+        };
+        let columns = self.preview_width.unwrap_or_else(|| {
+            std::env::var("COLUMNS")
+                .ok()
+                .and_then(|c| c.parse().ok())
+                .unwrap_or(80)
+        });
+        Some((format, columns))
+    }
+
+    /// Rozmiar docelowy dla konkretnego obrazu; przy jednym wymiarze zachowuje proporcje.
+    /// Bez flag obowiazuje rozmiar z przepisu, o ile jest ustawiony.
+    pub fn target_size(&self, settings: &DitherSettings, width: u32, height: u32) -> (u32, u32) {
+        let (width, height) = settings.cropped_size(width, height);
+        let aspect = width as f64 / height.max(1) as f64;
+        match (self.resize.width, self.resize.height) {
+            (None, None) if settings.target_width > 0 && settings.target_height > 0 => {
+                (settings.target_width, settings.target_height)
+            }
+            (Some(w), Some(h)) => (w, h),
+            (Some(w), None) => (w, (w as f64 / aspect).round().max(1.0) as u32),
+            (None, Some(h)) => ((h as f64 * aspect).round().max(1.0) as u32, h),
+            (None, None) => (width, height),
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone)]
+pub enum Input {
+    Stdin,
+    File {
+        path: PathBuf,
+        /// Podkatalog wzgledem przeszukiwanego katalogu, odtwarzany w katalogu wyjsciowym.
+        subdir: PathBuf,
+    },
+}
+
+impl Input {
+    pub fn label(&self) -> String {
+        match self {
+            Self::Stdin => "<stdin>".to_string(),
+            Self::File { path, .. } => path.display().to_string(),
+        }
+    }
+}
+
+fn is_image(path: &Path) -> bool {
+    image::ImageFormat::from_path(path).is_ok()
+}
+
+fn walk(root: &Path, dir: &Path, recursive: bool, out: &mut Vec<Input>) -> std::io::Result<()> {
+    let mut entries: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .collect();
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() {
+            if recursive {
+                walk(root, &path, recursive, out)?;
+            }
+        } else if is_image(&path) {
+            let subdir = dir
+                .strip_prefix(root)
+                .unwrap_or(Path::new(""))
+                .to_path_buf();
+            out.push(Input::File { path, subdir });
+        }
+    }
+    Ok(())
+}
+
+/// Rozwija argumenty na liste plikow; bledne wzorce trafiaja do listy bledow.
+pub fn collect(patterns: &[String], recursive: bool) -> (Vec<Input>, Vec<(String, String)>) {
+    let mut inputs = Vec::new();
+    let mut errors = Vec::new();
+
+    for pattern in patterns {
+        if pattern == "-" {
+            inputs.push(Input::Stdin);
+            continue;
+        }
+
+        let path = Path::new(pattern);
+        if path.is_dir() {
+            if let Err(e) = walk(path, path, recursive, &mut inputs) {
+                errors.push((pattern.clone(), e.to_string()));
+            }
+        } else if path.is_file() {
+            inputs.push(Input::File {
+                path: path.to_path_buf(),
+                subdir: PathBuf::new(),
+            });
+        } else if pattern.contains(['*', '?', '[']) {
+            match glob::glob(pattern) {
+                Ok(paths) => {
+                    let before = inputs.len();
+                    for path in paths.flatten().filter(|p| p.is_file() && is_image(p)) {
+                        inputs.push(Input::File {
+                            path,
+                            subdir: PathBuf::new(),
+                        });
+                    }
+                    if inputs.len() == before {
+                        errors.push((pattern.clone(), "no matching images".to_string()));
+                    }
+                }
+                Err(e) => errors.push((pattern.clone(), e.to_string())),
+            }
+        } else {
+            errors.push((pattern.clone(), "no such file or directory".to_string()));
+        }
+    }
+
+    (inputs, errors)
+}
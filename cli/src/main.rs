@@ -0,0 +1,324 @@
+mod args;
+mod inputs;
+mod watch;
+
+use args::{AlgorithmArg, Args, ModeArg};
+use clap::Parser;
+use dither_core::{DeviceFormat, DitherSettings, IndexedImage, Recipe};
+use image::{DynamicImage, ImageFormat};
+use inputs::Input;
+use rayon::prelude::*;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+/// Format zapisu: obraz albo bufor ramki dla wyswietlaczy (surowy lub jako kod).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Format {
+    Image(ImageFormat),
+    Device(DeviceFormat),
+    EscPos,
+}
+
+impl Format {
+    fn parse(name: &str) -> Option<Self> {
+        if name.eq_ignore_ascii_case("escpos") {
+            return Some(Self::EscPos);
+        }
+        DeviceFormat::from_extension(name)
+            .map(Self::Device)
+            .or_else(|| ImageFormat::from_extension(name).map(Self::Image))
+    }
+}
+
+enum Output {
+    Stdout,
+    File(PathBuf),
+    /// Plik lub urzadzenie dopisywane bez pliku tymczasowego (np. drukarka).
+    Device(PathBuf),
+    /// Tylko podglad w terminalu.
+    None,
+}
+
+// Algorytm i tryb z faktycznie uzytych ustawien, takze tych z przepisu
+fn render_name(
+    stem: &str,
+    name: &str,
+    args: &Args,
+    settings: &DitherSettings,
+    index: usize,
+) -> String {
+    args.name
+        .replace("{stem}", stem)
+        .replace("{name}", name)
+        .replace("{ext}", &args.format)
+        .replace("{algorithm}", &AlgorithmArg::name_of(settings.algorithm))
+        .replace("{mode}", &ModeArg::name_of(settings.mode))
+        .replace("{index}", &index.to_string())
+}
+
+fn output_for(input: &Input, args: &Args, settings: &DitherSettings, index: usize) -> Output {
+    if let Some(device) = &args.device {
+        return Output::Device(device.clone());
+    }
+    if args.output.as_deref() == Some(Path::new("-")) {
+        return Output::Stdout;
+    }
+    if args.output.is_none() && args.preview.is_some() {
+        return Output::None;
+    }
+
+    match input {
+        Input::Stdin => match &args.output {
+            Some(dir) => {
+                Output::File(dir.join(render_name("stdin", "stdin", args, settings, index)))
+            }
+            None => Output::Stdout,
+        },
+        Input::File { path, subdir } => {
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            let dir = match &args.output {
+                Some(dir) => dir.join(subdir),
+                None => path.parent().unwrap_or(Path::new("")).to_path_buf(),
+            };
+            Output::File(dir.join(render_name(&stem, &name, args, settings, index)))
+        }
+    }
+}
+
+fn read_input(input: &Input) -> Result<DynamicImage, String> {
+    match input {
+        Input::Stdin => {
+            let mut bytes = Vec::new();
+            std::io::stdin()
+                .read_to_end(&mut bytes)
+                .map_err(|e| e.to_string())?;
+            image::load_from_memory(&bytes).map_err(|e| e.to_string())
+        }
+        Input::File { path, .. } => image::open(path).map_err(|e| e.to_string()),
+    }
+}
+
+fn encode(
+    img: &DynamicImage,
+    format: Format,
+    name: &str,
+    args: &Args,
+    settings: &DitherSettings,
+) -> Result<Vec<u8>, String> {
+    match format {
+        // Przepis w metadanych pozwala pozniej odtworzyc ustawienia z samego pliku
+        Format::Image(format) => {
+            dither_core::encode_with_recipe(img, format, &Recipe::new(settings.clone()))
+                .map_err(|e| e.to_string())
+        }
+        Format::Device(format) => {
+            let indexed =
+                IndexedImage::from_image(img, &settings.palette()).map_err(|e| e.to_string())?;
+            format
+                .export(&indexed, &args.pack.options(), name)
+                .map_err(|e| e.to_string())
+        }
+        Format::EscPos => {
+            let indexed =
+                IndexedImage::from_image(img, &settings.palette()).map_err(|e| e.to_string())?;
+            dither_core::encode_escpos(&indexed, &args.printer.options()).map_err(|e| e.to_string())
+        }
+    }
+}
+
+fn write_output(bytes: &[u8], output: &Output) -> Result<(), String> {
+    match output {
+        Output::Stdout => {
+            let mut stdout = std::io::stdout().lock();
+            stdout
+                .write_all(bytes)
+                .and_then(|_| stdout.flush())
+                .map_err(|e| e.to_string())
+        }
+        Output::File(path) => {
+            if let Some(dir) = path.parent()
+                && !dir.as_os_str().is_empty()
+            {
+                std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+            }
+            write_atomic(path, |tmp| {
+                std::fs::write(tmp, bytes).map_err(|e| e.to_string())
+            })
+        }
+        Output::Device(path) => std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut device| device.write_all(bytes))
+            .map_err(|e| e.to_string()),
+        Output::None => Ok(()),
+    }
+}
+
+/// Zapis do pliku tymczasowego w tym samym katalogu i zmiana nazwy, zeby czytelnik
+/// nigdy nie zobaczyl niepelnego pliku.
+pub(crate) fn write_atomic(
+    path: &Path,
+    write: impl FnOnce(&Path) -> Result<(), String>,
+) -> Result<(), String> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let tmp = path.with_file_name(format!(".{name}.tmp"));
+    if let Err(e) = write(&tmp).and_then(|_| std::fs::rename(&tmp, path).map_err(|e| e.to_string()))
+    {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(e);
+    }
+    Ok(())
+}
+
+pub(crate) fn process(
+    input: &Input,
+    index: usize,
+    args: &Args,
+    settings: &DitherSettings,
+    format: Format,
+) -> Result<Option<PathBuf>, String> {
+    let img = read_input(input)?;
+
+    let (mut width, mut height) = args.target_size(settings, img.width(), img.height());
+    // Wydruk zawsze miesci sie w szerokosci glowicy
+    if format == Format::EscPos {
+        let printer = args.printer.options();
+        if args.resize.width.is_none() || width > printer.width {
+            (width, height) = printer.fit(width, height);
+        }
+    }
+    let settings = DitherSettings {
+        target_width: width,
+        target_height: height,
+        ..settings.clone()
+    };
+    let result = settings.process(&img);
+
+    let output = output_for(input, args, &settings, index);
+    // Nazwa tablicy w eksporcie do kodu zrodlowego
+    let name = match (&output, input) {
+        (Output::File(path), _) | (_, Input::File { path, .. }) => {
+            path.file_stem().unwrap_or_default().to_string_lossy()
+        }
+        (_, Input::Stdin) => "image".into(),
+    };
+    if !matches!(output, Output::None) {
+        write_output(&encode(&result, format, &name, args, &settings)?, &output)?;
+    }
+    if let Some((preview, columns)) = args.preview() {
+        let text = preview
+            .render(&result, &settings.palette(), Some(columns))
+            .map_err(|e| format!("preview: {e}"))?;
+        let mut stdout = std::io::stdout().lock();
+        writeln!(stdout, "{}:", input.label())
+            .and_then(|_| stdout.write_all(text.as_bytes()))
+            .and_then(|_| stdout.flush())
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(match output {
+        Output::Stdout | Output::None => None,
+        Output::File(path) | Output::Device(path) => Some(path),
+    })
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+
+    let settings = match args.settings() {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!("error: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    if let Some(path) = &args.save_recipe
+        && let Err(e) = dither_core::Recipe::new(settings.clone()).save(path)
+    {
+        eprintln!("error: {}: {e}", path.display());
+        return ExitCode::FAILURE;
+    }
+
+    let Some(format) = Format::parse(&args.format) else {
+        eprintln!("error: unsupported output format `{}`", args.format);
+        return ExitCode::FAILURE;
+    };
+
+    if args.jobs > 0 {
+        let _ = rayon::ThreadPoolBuilder::new()
+            .num_threads(args.jobs)
+            .build_global();
+    }
+
+    // Wyniki obok wejsc trafilyby przy kolejnym przebiegu na liste wejsc
+    if args.recursive && args.output.is_none() && args.device.is_none() && args.preview.is_none() {
+        eprintln!("error: --recursive needs an output directory (-o)");
+        return ExitCode::FAILURE;
+    }
+
+    if args.watch {
+        return match watch::run(&args, &settings, format) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("error: {e}");
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    let (inputs, mut failures) = inputs::collect(&args.inputs, args.recursive);
+    let to_stdout = args.output.as_deref() == Some(Path::new("-"))
+        || (args.output.is_none()
+            && args.device.is_none()
+            && args.preview.is_none()
+            && inputs.iter().any(|i| matches!(i, Input::Stdin)));
+    if to_stdout && args.preview.is_some() {
+        eprintln!("error: --preview also writes to stdout, choose an output directory");
+        return ExitCode::FAILURE;
+    }
+    if to_stdout && inputs.len() > 1 {
+        eprintln!("error: writing to stdout needs exactly one input");
+        return ExitCode::FAILURE;
+    }
+
+    let run = |(i, input): (usize, &Input)| {
+        (
+            input.label(),
+            process(input, i + 1, &args, &settings, format),
+        )
+    };
+    let sequential = args.device.is_some() || args.preview.is_some();
+    let results: Vec<(String, Result<Option<PathBuf>, String>)> = if sequential {
+        // Zadania dla jednego urzadzenia ani podglady w terminalu nie moga sie przeplatac
+        inputs.iter().enumerate().map(run).collect()
+    } else {
+        inputs.par_iter().enumerate().map(run).collect()
+    };
+
+    let mut processed = 0;
+    for (label, result) in results {
+        match result {
+            Ok(Some(path)) => {
+                processed += 1;
+                eprintln!("{label} -> {}", path.display());
+            }
+            Ok(None) => processed += 1,
+            Err(e) => failures.push((label, e)),
+        }
+    }
+
+    if !failures.is_empty() || processed != 1 {
+        eprintln!("{processed} processed, {} failed", failures.len());
+    }
+    for (label, error) in &failures {
+        eprintln!("  {label}: {error}");
+    }
+
+    if failures.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
@@ -0,0 +1,139 @@
+use crate::Format;
+use crate::args::Args;
+use crate::inputs::{self, Input};
+use dither_core::DitherSettings;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+const STATE_FILE: &str = ".dither-watch";
+
+// FNV-1a, stabilny miedzy uruchomieniami w przeciwienstwie do DefaultHasher
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |h, &b| {
+        (h ^ b as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// Odcisk wszystkiego, co wplywa na wynik poza samym plikiem wejsciowym.
+fn recipe_key(args: &Args, settings: &DitherSettings, format: Format) -> u64 {
+    let key = format!(
+        "{settings:?}|{format:?}|{}|{:?}|{:?}|{:?}|{:?}",
+        args.name,
+        args.resize.width,
+        args.resize.height,
+        args.pack.options(),
+        args.printer.options()
+    );
+    fnv1a(0xcbf2_9ce4_8422_2325, key.as_bytes())
+}
+
+fn fingerprint(path: &Path, recipe: u64) -> Option<u64> {
+    let meta = std::fs::metadata(path).ok()?;
+    let modified = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    let hash = fnv1a(recipe, &meta.len().to_le_bytes());
+    Some(fnv1a(hash, &modified.as_nanos().to_le_bytes()))
+}
+
+struct State {
+    path: PathBuf,
+    done: HashMap<PathBuf, u64>,
+}
+
+impl State {
+    fn load(dir: &Path) -> Self {
+        let path = dir.join(STATE_FILE);
+        let done = std::fs::read_to_string(&path)
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| {
+                let (hash, file) = line.split_once('\t')?;
+                Some((PathBuf::from(file), u64::from_str_radix(hash, 16).ok()?))
+            })
+            .collect();
+        Self { path, done }
+    }
+
+    fn save(&self) -> std::io::Result<()> {
+        let mut text = String::new();
+        for (file, hash) in &self.done {
+            text.push_str(&format!("{hash:016x}\t{}\n", file.display()));
+        }
+        crate::write_atomic(&self.path, |tmp| {
+            std::fs::write(tmp, &text).map_err(|e| e.to_string())
+        })
+        .map_err(std::io::Error::other)
+    }
+}
+
+/// Co `interval` sprawdza wejscia i przetwarza nowe lub zmienione pliki.
+pub fn run(args: &Args, settings: &DitherSettings, format: Format) -> Result<(), String> {
+    let Some(output) = args.output.as_deref() else {
+        return Err("watch mode needs an output directory (-o)".to_string());
+    };
+    if output == Path::new("-") || args.inputs.iter().any(|i| i == "-") {
+        return Err("watch mode cannot use stdin or stdout".to_string());
+    }
+    // Pozycja pliku zmienia sie, gdy pojawiaja sie nowe, wiec nazwy nie bylyby stale
+    if args.name.contains("{index}") {
+        return Err("watch mode cannot use {index} in --name".to_string());
+    }
+    std::fs::create_dir_all(output).map_err(|e| e.to_string())?;
+    let output_abs = std::fs::canonicalize(output).map_err(|e| e.to_string())?;
+
+    let recipe = recipe_key(args, settings, format);
+    let mut state = State::load(output);
+    // Plik jest przetwarzany dopiero, gdy jego odcisk nie zmienil sie miedzy dwoma
+    // przebiegami, zeby nie czytac obrazow, ktore wciaz sa kopiowane
+    let mut pending: HashMap<PathBuf, u64> = HashMap::new();
+    let mut failed: HashMap<PathBuf, u64> = HashMap::new();
+
+    eprintln!(
+        "watching {} every {}s",
+        args.inputs.join(", "),
+        args.interval
+    );
+    loop {
+        let (found, _) = inputs::collect(&args.inputs, args.recursive);
+        let mut changed = false;
+
+        for (index, input) in found.iter().enumerate() {
+            let Input::File { path, .. } = input else {
+                continue;
+            };
+            // Wyniki zapisane w obserwowanym katalogu nie moga wrocic na wejscie
+            if std::fs::canonicalize(path).is_ok_and(|p| p.starts_with(&output_abs)) {
+                continue;
+            }
+            let Some(print) = fingerprint(path, recipe) else {
+                continue;
+            };
+            if state.done.get(path) == Some(&print) || failed.get(path) == Some(&print) {
+                continue;
+            }
+            if pending.insert(path.clone(), print) != Some(print) {
+                continue;
+            }
+
+            pending.remove(path);
+            match crate::process(input, index + 1, args, settings, format) {
+                Ok(Some(out)) => {
+                    eprintln!("{} -> {}", path.display(), out.display());
+                    state.done.insert(path.clone(), print);
+                    failed.remove(path);
+                    changed = true;
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    eprintln!("{}: {e}", path.display());
+                    failed.insert(path.clone(), print);
+                }
+            }
+        }
+
+        if changed && let Err(e) = state.save() {
+            eprintln!("cannot save watch state: {e}");
+        }
+        std::thread::sleep(Duration::from_secs_f32(args.interval.max(0.1)));
+    }
+}
@@ -0,0 +1,68 @@
+/// Krzywa tonalna przez punkty kontrolne (wejscie, wyjscie), interpolowana
+/// monotonicznym Hermite'em (Fritsch-Carlson), wiec nie ma przestrzelen.
+pub fn curve_lut(points: &[(u8, u8)]) -> [u8; 256] {
+    let mut pts: Vec<(f32, f32)> = points.iter().map(|&(x, y)| (x as f32, y as f32)).collect();
+    pts.sort_by(|a, b| a.0.total_cmp(&b.0));
+    pts.dedup_by(|a, b| a.0 == b.0);
+
+    match pts.len() {
+        0 => return std::array::from_fn(|i| i as u8),
+        1 => return [pts[0].1 as u8; 256],
+        _ => {}
+    }
+
+    let n = pts.len();
+    let secants: Vec<f32> = pts
+        .windows(2)
+        .map(|w| (w[1].1 - w[0].1) / (w[1].0 - w[0].0))
+        .collect();
+
+    let mut tangents = vec![0.0f32; n];
+    tangents[0] = secants[0];
+    tangents[n - 1] = secants[n - 2];
+    for i in 1..n - 1 {
+        tangents[i] = if secants[i - 1] * secants[i] <= 0.0 {
+            0.0
+        } else {
+            (secants[i - 1] + secants[i]) / 2.0
+        };
+    }
+    for i in 0..n - 1 {
+        if secants[i] == 0.0 {
+            tangents[i] = 0.0;
+            tangents[i + 1] = 0.0;
+            continue;
+        }
+        let a = tangents[i] / secants[i];
+        let b = tangents[i + 1] / secants[i];
+        let s = a * a + b * b;
+        if s > 9.0 {
+            let t = 3.0 / s.sqrt();
+            tangents[i] = t * a * secants[i];
+            tangents[i + 1] = t * b * secants[i];
+        }
+    }
+
+    std::array::from_fn(|i| {
+        let x = i as f32;
+        if x <= pts[0].0 {
+            return pts[0].1 as u8;
+        }
+        if x >= pts[n - 1].0 {
+            return pts[n - 1].1 as u8;
+        }
+
+        let k = pts.partition_point(|p| p.0 <= x) - 1;
+        let (x0, y0) = pts[k];
+        let (x1, y1) = pts[k + 1];
+        let h = x1 - x0;
+        let t = (x - x0) / h;
+        let (t2, t3) = (t * t, t * t * t);
+
+        let y = (2.0 * t3 - 3.0 * t2 + 1.0) * y0
+            + (t3 - 2.0 * t2 + t) * h * tangents[k]
+            + (-2.0 * t3 + 3.0 * t2) * y1
+            + (t3 - t2) * h * tangents[k + 1];
+        y.round().clamp(0.0, 255.0) as u8
+    })
+}
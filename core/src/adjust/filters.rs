@@ -0,0 +1,161 @@
+use crate::convolve::{Border, gaussian_blur};
+use image::{DynamicImage, RgbaImage};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Denoise {
+    #[default]
+    None,
+    Median {
+        radius: u32,
+    },
+    /// Usrednia tylko piksele o podobnym kolorze, wiec nie rozmywa krawedzi.
+    Bilateral {
+        radius: u32,
+        sigma_color: f32,
+    },
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Sharpen {
+    /// Sila maski wyostrzajacej; 0 wylacza filtr.
+    pub amount: f32,
+    /// Sigma rozmycia gaussowskiego.
+    pub radius: f32,
+    /// Roznice mniejsze niz prog nie sa wzmacniane, zeby nie podbijac szumu.
+    pub threshold: u8,
+}
+
+impl Default for Sharpen {
+    fn default() -> Self {
+        Self {
+            amount: 0.0,
+            radius: 1.0,
+            threshold: 0,
+        }
+    }
+}
+
+fn neighborhood<F>(img: &RgbaImage, radius: u32, f: F) -> RgbaImage
+where
+    F: Fn(&RgbaImage, i64, i64, i64) -> [u8; 3] + Sync,
+{
+    let (width, height) = img.dimensions();
+    let mut buffer = img.as_raw().clone();
+
+    buffer
+        .par_chunks_exact_mut(width.max(1) as usize * 4)
+        .enumerate()
+        .for_each(|(y, row)| {
+            for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+                let rgb = f(img, x as i64, y as i64, radius as i64);
+                pixel[..3].copy_from_slice(&rgb);
+            }
+        });
+
+    RgbaImage::from_raw(width, height, buffer).unwrap()
+}
+
+fn clamped_pixel(img: &RgbaImage, x: i64, y: i64) -> &image::Rgba<u8> {
+    let x = x.clamp(0, img.width() as i64 - 1) as u32;
+    let y = y.clamp(0, img.height() as i64 - 1) as u32;
+    img.get_pixel(x, y)
+}
+
+pub fn median(img: &DynamicImage, radius: u32) -> DynamicImage {
+    if radius == 0 {
+        return img.clone();
+    }
+
+    let rgba = img.to_rgba8();
+    let out = neighborhood(&rgba, radius, |img, x, y, r| {
+        let mut window: [Vec<u8>; 3] = Default::default();
+        for dy in -r..=r {
+            for dx in -r..=r {
+                let p = clamped_pixel(img, x + dx, y + dy);
+                for c in 0..3 {
+                    window[c].push(p[c]);
+                }
+            }
+        }
+        window.map(|mut values| {
+            let mid = values.len() / 2;
+            *values.select_nth_unstable(mid).1
+        })
+    });
+    DynamicImage::ImageRgba8(out)
+}
+
+pub fn bilateral(img: &DynamicImage, radius: u32, sigma_color: f32) -> DynamicImage {
+    if radius == 0 {
+        return img.clone();
+    }
+
+    let sigma_space = radius as f32 / 2.0;
+    let space_k = -0.5 / (sigma_space * sigma_space);
+    let color_k = -0.5 / (sigma_color.max(0.1) * sigma_color.max(0.1));
+
+    let rgba = img.to_rgba8();
+    let out = neighborhood(&rgba, radius, |img, x, y, r| {
+        let center = clamped_pixel(img, x, y);
+        let mut sum = [0.0f32; 3];
+        let mut total = 0.0f32;
+
+        for dy in -r..=r {
+            for dx in -r..=r {
+                let p = clamped_pixel(img, x + dx, y + dy);
+                let dist = ((p[0] as f32 - center[0] as f32).abs()
+                    + (p[1] as f32 - center[1] as f32).abs()
+                    + (p[2] as f32 - center[2] as f32).abs())
+                    / 3.0;
+                let w = (space_k * (dx * dx + dy * dy) as f32 + color_k * dist * dist).exp();
+                for c in 0..3 {
+                    sum[c] += p[c] as f32 * w;
+                }
+                total += w;
+            }
+        }
+        sum.map(|v| (v / total).round().clamp(0.0, 255.0) as u8)
+    });
+    DynamicImage::ImageRgba8(out)
+}
+
+pub fn denoise(img: &DynamicImage, denoise: Denoise) -> DynamicImage {
+    match denoise {
+        Denoise::None => img.clone(),
+        Denoise::Median { radius } => median(img, radius),
+        Denoise::Bilateral {
+            radius,
+            sigma_color,
+        } => bilateral(img, radius, sigma_color),
+    }
+}
+
+pub fn unsharp_mask(img: &DynamicImage, sharpen: Sharpen) -> DynamicImage {
+    if sharpen.amount <= 0.0 {
+        return img.clone();
+    }
+
+    let rgba = img.to_rgba8();
+    let blurred = gaussian_blur(img, sharpen.radius, Border::Clamp).to_rgba8();
+    let (width, height) = rgba.dimensions();
+    let mut buffer = rgba.into_raw();
+
+    buffer
+        .par_chunks_exact_mut(4)
+        .zip(blurred.as_raw().par_chunks_exact(4))
+        .for_each(|(pixel, blur)| {
+            for c in 0..3 {
+                let diff = pixel[c] as f32 - blur[c] as f32;
+                if diff.abs() >= sharpen.threshold as f32 {
+                    let v = pixel[c] as f32 + diff * sharpen.amount;
+                    pixel[c] = v.round().clamp(0.0, 255.0) as u8;
+                }
+            }
+        });
+
+    DynamicImage::ImageRgba8(RgbaImage::from_raw(width, height, buffer).unwrap())
+}
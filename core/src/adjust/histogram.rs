@@ -0,0 +1,194 @@
+use crate::luma::LumaModel;
+use image::{DynamicImage, RgbaImage};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Equalization {
+    #[default]
+    None,
+    Global,
+    /// Adaptacyjne wyrownanie w kafelkach `tiles` x `tiles`, histogram obciety
+    /// na `clip_limit` razy srednia liczba pikseli w przedziale.
+    Clahe {
+        tiles: u32,
+        clip_limit: f32,
+    },
+    /// Rozciagniecie histogramu po odcieciu `clip` procent pikseli z kazdej strony.
+    AutoLevels {
+        clip: f32,
+    },
+}
+
+pub fn histogram(img: &DynamicImage) -> [u32; 256] {
+    let rgb = img.to_rgb8();
+    rgb.as_raw()
+        .par_chunks_exact(3)
+        .fold(
+            || [0u32; 256],
+            |mut hist, p| {
+                hist[LumaModel::default().luma([p[0], p[1], p[2]]) as usize] += 1;
+                hist
+            },
+        )
+        .reduce(
+            || [0u32; 256],
+            |mut a, b| {
+                for (x, y) in a.iter_mut().zip(b) {
+                    *x += y;
+                }
+                a
+            },
+        )
+}
+
+fn cdf_lut(hist: &[u32; 256]) -> [u8; 256] {
+    let total: u32 = hist.iter().sum();
+    let first = hist.iter().copied().find(|&c| c > 0).unwrap_or(0);
+    if total <= first {
+        return std::array::from_fn(|i| i as u8);
+    }
+
+    let mut acc = 0u32;
+    let mut lut = [0u8; 256];
+    for (v, &count) in hist.iter().enumerate() {
+        acc += count;
+        lut[v] =
+            ((acc.saturating_sub(first) as f32 / (total - first) as f32) * 255.0).round() as u8;
+    }
+    lut
+}
+
+fn auto_levels_lut(hist: &[u32; 256], clip: f32) -> [u8; 256] {
+    let total: u32 = hist.iter().sum();
+    let cut = (total as f32 * clip.clamp(0.0, 49.0) / 100.0) as u32;
+
+    let mut acc = 0;
+    let low = hist
+        .iter()
+        .position(|&c| {
+            acc += c;
+            acc > cut
+        })
+        .unwrap_or(0);
+    let mut acc = 0;
+    let high = 255
+        - hist
+            .iter()
+            .rev()
+            .position(|&c| {
+                acc += c;
+                acc > cut
+            })
+            .unwrap_or(0);
+
+    if high <= low {
+        return std::array::from_fn(|i| i as u8);
+    }
+    std::array::from_fn(|i| {
+        let v = (i as f32 - low as f32) / (high - low) as f32 * 255.0;
+        v.round().clamp(0.0, 255.0) as u8
+    })
+}
+
+/// LUT dla metod globalnych; CLAHE dziala przestrzennie i zwraca tozsamosc.
+pub fn equalization_lut(img: &DynamicImage, eq: Equalization) -> [u8; 256] {
+    match eq {
+        Equalization::Global => cdf_lut(&histogram(img)),
+        Equalization::AutoLevels { clip } => auto_levels_lut(&histogram(img), clip),
+        Equalization::None | Equalization::Clahe { .. } => std::array::from_fn(|i| i as u8),
+    }
+}
+
+fn clipped_lut(hist: [u32; 256], clip_limit: f32) -> [u8; 256] {
+    let total: u32 = hist.iter().sum();
+    let limit = clip_limit.max(1.0) * total as f32 / 256.0;
+
+    let mut hist = hist.map(|c| c as f32);
+    let mut excess = 0.0;
+    for count in hist.iter_mut() {
+        if *count > limit {
+            excess += *count - limit;
+            *count = limit;
+        }
+    }
+
+    let share = excess / 256.0;
+    let mut acc = 0.0;
+    std::array::from_fn(|v| {
+        acc += hist[v] + share;
+        ((acc / total.max(1) as f32) * 255.0)
+            .round()
+            .clamp(0.0, 255.0) as u8
+    })
+}
+
+/// CLAHE na jasnosci; ta sama krzywa kafelka jest stosowana do wszystkich kanalow.
+pub fn clahe(img: &DynamicImage, tiles: u32, clip_limit: f32) -> DynamicImage {
+    let rgba = img.to_rgba8();
+    let (width, height) = rgba.dimensions();
+    if width == 0 || height == 0 {
+        return img.clone();
+    }
+
+    let tile_w = (width as usize).div_ceil(tiles.clamp(1, width) as usize);
+    let tile_h = (height as usize).div_ceil(tiles.clamp(1, height) as usize);
+    // Po zaokragleniu rozmiaru kafelka ostatnie kafelki moglyby byc puste
+    let tiles_x = (width as usize).div_ceil(tile_w);
+    let tiles_y = (height as usize).div_ceil(tile_h);
+
+    let luma: Vec<u8> = rgba
+        .as_raw()
+        .par_chunks_exact(4)
+        .map(|p| LumaModel::default().luma([p[0], p[1], p[2]]))
+        .collect();
+
+    let luts: Vec<[u8; 256]> = (0..tiles_x * tiles_y)
+        .into_par_iter()
+        .map(|t| {
+            let (tx, ty) = (t % tiles_x, t / tiles_x);
+            let mut hist = [0u32; 256];
+            for y in ty * tile_h..((ty + 1) * tile_h).min(height as usize) {
+                for x in tx * tile_w..((tx + 1) * tile_w).min(width as usize) {
+                    hist[luma[y * width as usize + x] as usize] += 1;
+                }
+            }
+            clipped_lut(hist, clip_limit)
+        })
+        .collect();
+
+    // Pozycja piksela wzgledem srodkow kafelkow: indeks lewego/gornego i waga
+    let locate = |p: usize, size: usize, count: usize| -> (usize, usize, f32) {
+        let f = (p as f32 + 0.5) / size as f32 - 0.5;
+        if f <= 0.0 {
+            return (0, 0, 0.0);
+        }
+        let i = f.floor() as usize;
+        if i + 1 >= count {
+            return (count - 1, count - 1, 0.0);
+        }
+        (i, i + 1, f - i as f32)
+    };
+
+    let mut buffer = rgba.into_raw();
+    buffer
+        .par_chunks_exact_mut(width as usize * 4)
+        .enumerate()
+        .for_each(|(y, row)| {
+            let (y0, y1, fy) = locate(y, tile_h, tiles_y);
+            for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+                let (x0, x1, fx) = locate(x, tile_w, tiles_x);
+                for v in &mut pixel[..3] {
+                    let i = *v as usize;
+                    let top = luts[y0 * tiles_x + x0][i] as f32 * (1.0 - fx)
+                        + luts[y0 * tiles_x + x1][i] as f32 * fx;
+                    let bottom = luts[y1 * tiles_x + x0][i] as f32 * (1.0 - fx)
+                        + luts[y1 * tiles_x + x1][i] as f32 * fx;
+                    *v = (top * (1.0 - fy) + bottom * fy).round() as u8;
+                }
+            }
+        });
+
+    DynamicImage::ImageRgba8(RgbaImage::from_raw(width, height, buffer).unwrap())
+}
@@ -0,0 +1,103 @@
+pub mod curve;
+pub mod filters;
+pub mod histogram;
+
+use filters::{Denoise, Sharpen};
+use histogram::Equalization;
+use image::{DynamicImage, RgbaImage};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Korekty wykonywane przed ditheringiem, zawsze w tej samej kolejnosci: odszumianie,
+/// wyrownanie histogramu, poziomy (czern/biel), jasnosc, kontrast, gamma, krzywa, wyostrzanie.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Adjustments {
+    pub denoise: Denoise,
+    pub equalization: Equalization,
+    /// Przesuniecie dodawane do kazdego kanalu, -255..=255.
+    pub brightness: f32,
+    /// Kontrast w procentach, ta sama skala co `imageops::contrast`.
+    pub contrast: f32,
+    pub gamma: f32,
+    pub black_point: u8,
+    pub white_point: u8,
+    /// Punkty kontrolne (wejscie, wyjscie); pusta lista to brak krzywej.
+    pub curve: Vec<(u8, u8)>,
+    pub sharpen: Sharpen,
+}
+
+impl Default for Adjustments {
+    fn default() -> Self {
+        Self {
+            denoise: Denoise::None,
+            equalization: Equalization::None,
+            brightness: 0.0,
+            contrast: 0.0,
+            gamma: 1.0,
+            black_point: 0,
+            white_point: 255,
+            curve: Vec::new(),
+            sharpen: Sharpen::default(),
+        }
+    }
+}
+
+impl Adjustments {
+    pub fn is_identity(&self) -> bool {
+        self.denoise == Denoise::None
+            && self.equalization == Equalization::None
+            && self.sharpen.amount <= 0.0
+            && self.lut().iter().enumerate().all(|(i, &v)| i == v as usize)
+    }
+
+    pub fn lut(&self) -> [u8; 256] {
+        let curve = curve::curve_lut(&self.curve);
+        let black = self.black_point as f32;
+        let white = (self.white_point as f32).max(black + 1.0);
+        let contrast = ((100.0 + self.contrast) / 100.0).powi(2);
+        let gamma = self.gamma.max(0.01);
+
+        std::array::from_fn(|i| {
+            let mut v = ((i as f32 - black) / (white - black)).clamp(0.0, 1.0) * 255.0;
+            v = (v + self.brightness).clamp(0.0, 255.0);
+            v = (((v / 255.0 - 0.5) * contrast + 0.5) * 255.0).clamp(0.0, 255.0);
+            v = (v / 255.0).powf(1.0 / gamma) * 255.0;
+            curve[v.round().clamp(0.0, 255.0) as usize]
+        })
+    }
+}
+
+pub fn apply_lut(img: &DynamicImage, lut: &[u8; 256]) -> DynamicImage {
+    let rgba = img.to_rgba8();
+    let (width, height) = rgba.dimensions();
+    let mut buffer = rgba.into_raw();
+
+    buffer.par_chunks_exact_mut(4).for_each(|pixel| {
+        pixel[0] = lut[pixel[0] as usize];
+        pixel[1] = lut[pixel[1] as usize];
+        pixel[2] = lut[pixel[2] as usize];
+    });
+
+    let img_out = RgbaImage::from_raw(width, height, buffer).unwrap();
+    DynamicImage::ImageRgba8(img_out)
+}
+
+pub fn adjust(img: &DynamicImage, adjustments: &Adjustments) -> DynamicImage {
+    if adjustments.is_identity() {
+        return img.clone();
+    }
+
+    let mut img = filters::denoise(img, adjustments.denoise);
+
+    if let Equalization::Clahe { tiles, clip_limit } = adjustments.equalization {
+        img = histogram::clahe(&img, tiles, clip_limit);
+    }
+
+    let pre = histogram::equalization_lut(&img, adjustments.equalization);
+    let tone = adjustments.lut();
+    let lut: [u8; 256] = std::array::from_fn(|i| tone[pre[i] as usize]);
+    let img = apply_lut(&img, &lut);
+
+    filters::unsharp_mask(&img, adjustments.sharpen)
+}
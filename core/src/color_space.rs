@@ -0,0 +1,132 @@
+use crate::luma::{linear_to_srgb, srgb_u8_to_linear};
+use image::{DynamicImage, RgbImage};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Przestrzen, w ktorej kolorowy dithering kwantyzuje kanaly.
+#[derive(Debug, PartialEq, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DitherSpace {
+    /// Kazdy kanal RGB osobno.
+    #[default]
+    Rgb,
+    /// Tylko Y (BT.601), Cb i Cr zostaja z oryginalu.
+    YCbCr,
+    /// Tylko L z OKLab, a i b zostaja z oryginalu.
+    OkLab,
+    /// Kanaly C, M, Y, czyli odwrocone RGB.
+    Cmy,
+}
+
+impl DitherSpace {
+    pub fn is_luma_only(self) -> bool {
+        matches!(self, Self::YCbCr | Self::OkLab)
+    }
+
+    // Kanaly w skali 0..=255, jeszcze bez zaokraglenia
+    fn encode(self, rgb: [u8; 3]) -> [f32; 3] {
+        match self {
+            Self::Rgb => rgb.map(|c| c as f32),
+            Self::Cmy => rgb.map(|c| (255 - c) as f32),
+            Self::YCbCr => {
+                let [r, g, b] = rgb.map(|c| c as f32);
+                let y = 0.299 * r + 0.587 * g + 0.114 * b;
+                let cb = 128.0 - 0.168_736 * r - 0.331_264 * g + 0.5 * b;
+                let cr = 128.0 + 0.5 * r - 0.418_688 * g - 0.081_312 * b;
+                [y, cb, cr]
+            }
+            Self::OkLab => {
+                let [l, a, b] = linear_srgb_to_oklab(rgb.map(srgb_u8_to_linear));
+                [l * 255.0, a * 255.0 + 128.0, b * 255.0 + 128.0]
+            }
+        }
+    }
+
+    fn decode(self, v: [f32; 3]) -> [u8; 3] {
+        match self {
+            Self::Rgb => v.map(to_u8),
+            Self::Cmy => v.map(|c| 255 - to_u8(c)),
+            Self::YCbCr => {
+                let [y, cb, cr] = v;
+                let r = y + 1.402 * (cr - 128.0);
+                let g = y - 0.344_136 * (cb - 128.0) - 0.714_136 * (cr - 128.0);
+                let b = y + 1.772 * (cb - 128.0);
+                [r, g, b].map(to_u8)
+            }
+            Self::OkLab => {
+                let [l, a, b] = v;
+                let lab = [l / 255.0, (a - 128.0) / 255.0, (b - 128.0) / 255.0];
+                oklab_to_linear_srgb(lab).map(|c| to_u8(linear_to_srgb(c.clamp(0.0, 1.0)) * 255.0))
+            }
+        }
+    }
+}
+
+fn to_u8(v: f32) -> u8 {
+    v.round().clamp(0.0, 255.0) as u8
+}
+
+// Ottosson 2020
+fn linear_srgb_to_oklab([r, g, b]: [f32; 3]) -> [f32; 3] {
+    let l = (0.412_221_47 * r + 0.536_332_55 * g + 0.051_445_995 * b).cbrt();
+    let m = (0.211_903_5 * r + 0.680_699_5 * g + 0.107_396_96 * b).cbrt();
+    let s = (0.088_302_46 * r + 0.281_718_85 * g + 0.629_978_7 * b).cbrt();
+    [
+        0.210_454_26 * l + 0.793_617_8 * m - 0.004_072_047 * s,
+        1.977_998_5 * l - 2.428_592_2 * m + 0.450_593_7 * s,
+        0.025_904_037 * l + 0.782_771_77 * m - 0.808_675_77 * s,
+    ]
+}
+
+fn oklab_to_linear_srgb([l, a, b]: [f32; 3]) -> [f32; 3] {
+    let l_ = (l + 0.396_337_78 * a + 0.215_803_76 * b).powi(3);
+    let m_ = (l - 0.105_561_346 * a - 0.063_854_17 * b).powi(3);
+    let s_ = (l - 0.089_484_18 * a - 1.291_485_5 * b).powi(3);
+    [
+        4.076_741_7 * l_ - 3.307_711_6 * m_ + 0.230_969_94 * s_,
+        -1.268_438 * l_ + 2.609_757_4 * m_ - 0.341_319_38 * s_,
+        -0.004_196_086_3 * l_ - 0.703_418_6 * m_ + 1.707_614_7 * s_,
+    ]
+}
+
+/// Przepuszcza dowolny kolorowy algorytm przez wybrana przestrzen barw.
+/// Dla przestrzeni luma-only z wyniku brany jest tylko pierwszy kanal.
+pub fn dither_in_space<F>(img: DynamicImage, space: DitherSpace, dither: F) -> DynamicImage
+where
+    F: FnOnce(DynamicImage) -> DynamicImage,
+{
+    if space == DitherSpace::Rgb {
+        return dither(img);
+    }
+
+    let rgb = img.to_rgb8();
+    let (width, height) = rgb.dimensions();
+
+    let mut encoded = rgb.as_raw().clone();
+    encoded.par_chunks_exact_mut(3).for_each(|pixel| {
+        let v = space.encode([pixel[0], pixel[1], pixel[2]]);
+        pixel.copy_from_slice(&v.map(to_u8));
+    });
+
+    let encoded = RgbImage::from_raw(width, height, encoded).unwrap();
+    let mut dithered = dither(DynamicImage::ImageRgb8(encoded))
+        .to_rgb8()
+        .into_raw();
+
+    // Chroma liczona ponownie z oryginalu, zeby nie tracic precyzji na u8
+    dithered
+        .par_chunks_exact_mut(3)
+        .zip(rgb.as_raw().par_chunks_exact(3))
+        .for_each(|(out, src)| {
+            let mut v = [out[0] as f32, out[1] as f32, out[2] as f32];
+            if space.is_luma_only() {
+                let orig = space.encode([src[0], src[1], src[2]]);
+                v[1] = orig[1];
+                v[2] = orig[2];
+            }
+            out.copy_from_slice(&space.decode(v));
+        });
+
+    let img_out = RgbImage::from_raw(width, height, dithered).unwrap();
+    DynamicImage::ImageRgb8(img_out)
+}
@@ -0,0 +1,194 @@
+use image::{DynamicImage, RgbaImage};
+use rayon::prelude::*;
+
+/// Sposob odczytu pikseli spoza obrazu.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum Border {
+    /// Powtarza skrajny piksel.
+    #[default]
+    Clamp,
+    /// Odbicie lustrzane bez powtarzania skrajnego piksela.
+    Mirror,
+    /// Obraz powtarza sie okresowo.
+    Wrap,
+    /// Poza obrazem same zera.
+    Zero,
+}
+
+impl Border {
+    fn index(self, i: i64, n: usize) -> Option<usize> {
+        let n = n as i64;
+        if (0..n).contains(&i) {
+            return Some(i as usize);
+        }
+        match self {
+            Self::Clamp => Some(i.clamp(0, n - 1) as usize),
+            Self::Mirror => {
+                if n == 1 {
+                    return Some(0);
+                }
+                let period = 2 * (n - 1);
+                let i = i.rem_euclid(period);
+                Some(if i < n { i } else { period - i } as usize)
+            }
+            Self::Wrap => Some(i.rem_euclid(n) as usize),
+            Self::Zero => None,
+        }
+    }
+}
+
+/// Jadro splotu o nieparzystych wymiarach, zakotwiczone w srodku.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Kernel {
+    pub values: Vec<f32>,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Kernel {
+    pub fn new(width: usize, height: usize, values: Vec<f32>) -> Self {
+        assert_eq!(values.len(), width * height, "kernel size mismatch");
+        Self {
+            values,
+            width,
+            height,
+        }
+    }
+
+    pub fn sobel_x() -> Self {
+        Self::new(3, 3, vec![-1.0, 0.0, 1.0, -2.0, 0.0, 2.0, -1.0, 0.0, 1.0])
+    }
+
+    pub fn sobel_y() -> Self {
+        Self::new(3, 3, vec![-1.0, -2.0, -1.0, 0.0, 0.0, 0.0, 1.0, 2.0, 1.0])
+    }
+
+    pub fn laplacian() -> Self {
+        Self::new(3, 3, vec![0.0, 1.0, 0.0, 1.0, -4.0, 1.0, 0.0, 1.0, 0.0])
+    }
+}
+
+/// Znormalizowane jadro 1D sredniej o szerokosci `2 * radius + 1`.
+pub fn box_kernel(radius: usize) -> Vec<f32> {
+    let size = 2 * radius + 1;
+    vec![1.0 / size as f32; size]
+}
+
+/// Znormalizowane jadro 1D Gaussa, obciete na 3 sigma.
+pub fn gaussian_kernel(sigma: f32) -> Vec<f32> {
+    let sigma = sigma.max(0.1);
+    let radius = (sigma * 3.0).ceil() as i64;
+    let weights: Vec<f32> = (-radius..=radius)
+        .map(|i| (-(i * i) as f32 / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let total: f32 = weights.iter().sum();
+    weights.into_iter().map(|w| w / total).collect()
+}
+
+/// Splot bufora z przeplecionymi kanalami (uklad jak w `RgbImage::as_raw`),
+/// kazdy kanal liczony osobno. Wynik w f32, bo jadra moga dawac wartosci ujemne.
+pub fn convolve<T>(
+    buffer: &[T],
+    width: usize,
+    height: usize,
+    channels: usize,
+    kernel: &Kernel,
+    border: Border,
+) -> Vec<f32>
+where
+    T: Copy + Into<f32> + Sync,
+{
+    let mut out = vec![0.0f32; width * height * channels];
+    if out.is_empty() {
+        return out;
+    }
+    let (ax, ay) = ((kernel.width / 2) as i64, (kernel.height / 2) as i64);
+
+    out.par_chunks_exact_mut(width * channels)
+        .enumerate()
+        .for_each(|(y, row)| {
+            for x in 0..width {
+                for (k, &weight) in kernel.values.iter().enumerate() {
+                    if weight == 0.0 {
+                        continue;
+                    }
+                    let dx = (k % kernel.width) as i64 - ax;
+                    let dy = (k / kernel.width) as i64 - ay;
+                    let (Some(sx), Some(sy)) = (
+                        border.index(x as i64 + dx, width),
+                        border.index(y as i64 + dy, height),
+                    ) else {
+                        continue;
+                    };
+                    let src = (sy * width + sx) * channels;
+                    for c in 0..channels {
+                        row[x * channels + c] += buffer[src + c].into() * weight;
+                    }
+                }
+            }
+        });
+
+    out
+}
+
+/// Splot separowalny: najpierw `horizontal` w wierszach, potem `vertical` w kolumnach.
+pub fn convolve_separable<T>(
+    buffer: &[T],
+    width: usize,
+    height: usize,
+    channels: usize,
+    horizontal: &[f32],
+    vertical: &[f32],
+    border: Border,
+) -> Vec<f32>
+where
+    T: Copy + Into<f32> + Sync,
+{
+    let rows = convolve(
+        buffer,
+        width,
+        height,
+        channels,
+        &Kernel::new(horizontal.len(), 1, horizontal.to_vec()),
+        border,
+    );
+    convolve(
+        &rows,
+        width,
+        height,
+        channels,
+        &Kernel::new(1, vertical.len(), vertical.to_vec()),
+        border,
+    )
+}
+
+pub fn to_u8(values: &[f32]) -> Vec<u8> {
+    values
+        .par_iter()
+        .map(|v| v.round().clamp(0.0, 255.0) as u8)
+        .collect()
+}
+
+/// Rozmycie Gaussa kanalow RGB, kanal alfa bez zmian.
+pub fn gaussian_blur(img: &DynamicImage, sigma: f32, border: Border) -> DynamicImage {
+    let rgba = img.to_rgba8();
+    let (width, height) = rgba.dimensions();
+    let kernel = gaussian_kernel(sigma);
+    let blurred = to_u8(&convolve_separable(
+        rgba.as_raw(),
+        width as usize,
+        height as usize,
+        4,
+        &kernel,
+        &kernel,
+        border,
+    ));
+
+    let mut buffer = rgba.into_raw();
+    buffer
+        .par_chunks_exact_mut(4)
+        .zip(blurred.par_chunks_exact(4))
+        .for_each(|(pixel, blur)| pixel[..3].copy_from_slice(&blur[..3]));
+
+    DynamicImage::ImageRgba8(RgbaImage::from_raw(width, height, buffer).unwrap())
+}
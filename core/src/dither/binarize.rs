@@ -0,0 +1,128 @@
+use crate::convolve::{Border, box_kernel, convolve_separable};
+use crate::luma::{LumaModel, to_luma};
+use image::{DynamicImage, GrayImage, RgbImage};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Progowanie bez ditheringu, do skanow dokumentow.
+#[derive(Debug, PartialEq, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Binarization {
+    /// Globalny prog maksymalizujacy wariancje miedzyklasowa.
+    #[default]
+    Otsu,
+    /// `T = m * (1 + k * (s / 128 - 1))`, dobrze znosi nierowne tlo.
+    Sauvola { radius: u32, k: f32 },
+    /// `T = m + k * s`.
+    Niblack { radius: u32, k: f32 },
+    /// Piksel jest ciemny, gdy jest o `t` ciemniejszy od sredniej z okna.
+    Bradley { radius: u32, t: f32 },
+}
+
+pub fn otsu_threshold(hist: &[u32; 256]) -> u8 {
+    let total: f64 = hist.iter().map(|&c| c as f64).sum();
+    let sum_all: f64 = hist
+        .iter()
+        .enumerate()
+        .map(|(v, &c)| v as f64 * c as f64)
+        .sum();
+
+    let mut best = (0.0, 0u8);
+    let mut weight_low = 0.0;
+    let mut sum_low = 0.0;
+    for (v, &count) in hist.iter().enumerate() {
+        weight_low += count as f64;
+        sum_low += v as f64 * count as f64;
+        let weight_high = total - weight_low;
+        if weight_low == 0.0 || weight_high == 0.0 {
+            continue;
+        }
+        let mean_low = sum_low / weight_low;
+        let mean_high = (sum_all - sum_low) / weight_high;
+        let between = weight_low * weight_high * (mean_low - mean_high).powi(2);
+        if between > best.0 {
+            best = (between, v as u8);
+        }
+    }
+    best.1
+}
+
+// Srednia i odchylenie standardowe w oknie (2r+1)x(2r+1) wokol kazdego piksela
+fn local_stats(gray: &GrayImage, radius: u32) -> (Vec<f32>, Vec<f32>) {
+    let (width, height) = (gray.width() as usize, gray.height() as usize);
+    let kernel = box_kernel(radius as usize);
+    let mean = convolve_separable(
+        gray.as_raw(),
+        width,
+        height,
+        1,
+        &kernel,
+        &kernel,
+        Border::Mirror,
+    );
+    let squares: Vec<f32> = gray.as_raw().iter().map(|&v| v as f32 * v as f32).collect();
+    let mean_sq = convolve_separable(&squares, width, height, 1, &kernel, &kernel, Border::Mirror);
+
+    let std = mean
+        .par_iter()
+        .zip(mean_sq.par_iter())
+        .map(|(m, sq)| (sq - m * m).max(0.0).sqrt())
+        .collect();
+    (mean, std)
+}
+
+/// Prog dla kazdego piksela; wartosc wieksza od progu staje sie jasna.
+pub fn thresholds(gray: &GrayImage, method: Binarization) -> Vec<f32> {
+    let len = gray.as_raw().len();
+    match method {
+        Binarization::Otsu => {
+            let mut hist = [0u32; 256];
+            for &v in gray.as_raw() {
+                hist[v as usize] += 1;
+            }
+            vec![otsu_threshold(&hist) as f32; len]
+        }
+        Binarization::Sauvola { radius, k } => {
+            let (mean, std) = local_stats(gray, radius);
+            mean.par_iter()
+                .zip(std.par_iter())
+                .map(|(m, s)| m * (1.0 + k * (s / 128.0 - 1.0)))
+                .collect()
+        }
+        Binarization::Niblack { radius, k } => {
+            let (mean, std) = local_stats(gray, radius);
+            mean.par_iter()
+                .zip(std.par_iter())
+                .map(|(m, s)| m + k * s)
+                .collect()
+        }
+        Binarization::Bradley { radius, t } => {
+            let (mean, _) = local_stats(gray, radius);
+            mean.par_iter().map(|m| m * (1.0 - t)).collect()
+        }
+    }
+}
+
+pub fn dither_duoton(
+    method: Binarization,
+    img: &DynamicImage,
+    low: [u8; 3],
+    high: [u8; 3],
+    luma: LumaModel,
+) -> DynamicImage {
+    let gray = to_luma(img, luma);
+    let (width, height) = gray.dimensions();
+    let thresholds = thresholds(&gray, method);
+    let mut buffer = vec![0u8; (width * height * 3) as usize];
+
+    buffer
+        .par_chunks_exact_mut(3)
+        .zip(gray.as_raw().par_iter())
+        .zip(thresholds.par_iter())
+        .for_each(|((pixel, &value), &threshold)| {
+            let color = if value as f32 > threshold { high } else { low };
+            pixel.copy_from_slice(&color);
+        });
+
+    DynamicImage::ImageRgb8(RgbImage::from_raw(width, height, buffer).unwrap())
+}
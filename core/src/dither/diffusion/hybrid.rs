@@ -0,0 +1,124 @@
+use super::params::DiffusionParams;
+use crate::dither::ordered::threshold_map::ThresholdMap;
+use crate::luma::{LumaModel, to_luma};
+use image::{DynamicImage, Rgb, RgbImage};
+
+// blend = 0.0 to czysty dithering uporzadkowany, 1.0 to czysty Floyd-Steinberg
+pub(crate) fn hybrid_threshold(
+    map: &ThresholdMap,
+    params: &DiffusionParams,
+    blend: f32,
+    x: usize,
+    y: usize,
+) -> i16 {
+    let ordered = (map.value_at(x, y) as u32 * 255 / map.levels) as f32 - 127.0;
+    params.threshold_at(x, y) + (ordered * (1.0 - blend)).round() as i16
+}
+
+pub(crate) fn hybrid_error(params: &DiffusionParams, blend: f32, err: i16) -> i16 {
+    (params.scale_error(err) as f32 * blend).round() as i16
+}
+
+pub(crate) fn diffuse(
+    buffer: &mut [i16],
+    w: usize,
+    h: usize,
+    x: usize,
+    y: usize,
+    stride: usize,
+    err: i16,
+) {
+    let idx = (y * w + x) * stride;
+
+    if x + 1 < w {
+        let i = idx + stride;
+        buffer[i] = buffer[i].saturating_add((err * 7) >> 4);
+    }
+    if y + 1 < h {
+        let down = idx + w * stride;
+        if x > 0 {
+            let i = down - stride;
+            buffer[i] = buffer[i].saturating_add((err * 3) >> 4);
+        }
+        buffer[down] = buffer[down].saturating_add((err * 5) >> 4);
+        if x + 1 < w {
+            let i = down + stride;
+            buffer[i] = buffer[i].saturating_add(err >> 4);
+        }
+    }
+}
+
+pub fn dither_colored(
+    map: &ThresholdMap,
+    img: &DynamicImage,
+    params: DiffusionParams,
+    blend: f32,
+) -> DynamicImage {
+    let blend = blend.clamp(0.0, 1.0);
+    let rgb = img.to_rgb8();
+    let (w, h) = rgb.dimensions();
+    let w = w as usize;
+    let h = h as usize;
+
+    let mut buffer: Vec<i16> = rgb.as_raw().iter().map(|&b| b as i16).collect();
+
+    for y in 0..h {
+        for x in 0..w {
+            let idx = (y * w + x) * 3;
+            let threshold = hybrid_threshold(map, &params, blend, x, y);
+
+            for c in 0..3 {
+                let old_val = buffer[idx + c];
+                let new_val = if old_val > threshold { 255 } else { 0 };
+                buffer[idx + c] = new_val;
+
+                let err = hybrid_error(&params, blend, old_val - new_val);
+                diffuse(&mut buffer[c..], w, h, x, y, 3, err);
+            }
+        }
+    }
+
+    let raw_u8: Vec<u8> = buffer.into_iter().map(|v| v.clamp(0, 255) as u8).collect();
+
+    let img_out = RgbImage::from_raw(w as u32, h as u32, raw_u8).unwrap();
+    DynamicImage::ImageRgb8(img_out)
+}
+
+pub fn dither_duoton(
+    map: &ThresholdMap,
+    img: &DynamicImage,
+    low: [u8; 3],
+    high: [u8; 3],
+    luma: LumaModel,
+    params: DiffusionParams,
+    blend: f32,
+) -> DynamicImage {
+    let blend = blend.clamp(0.0, 1.0);
+    let gray = to_luma(img, luma);
+    let (w, h) = gray.dimensions();
+    let w = w as usize;
+    let h = h as usize;
+
+    let mut err_buffer: Vec<i16> = gray.as_raw().iter().map(|&x| x as i16).collect();
+
+    let mut out_img = RgbImage::new(w as u32, h as u32);
+
+    for y in 0..h {
+        for x in 0..w {
+            let old_val = err_buffer[y * w + x];
+
+            let (target_val_u8, color) = if old_val > hybrid_threshold(map, &params, blend, x, y) {
+                (255, high)
+            } else {
+                (0, low)
+            };
+
+            out_img.put_pixel(x as u32, y as u32, Rgb(color));
+
+            let err = hybrid_error(&params, blend, old_val - target_val_u8);
+            diffuse(&mut err_buffer, w, h, x, y, 1, err);
+        }
+    }
+
+    DynamicImage::ImageRgb8(out_img)
+}
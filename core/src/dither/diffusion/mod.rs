@@ -1 +1,3 @@
 pub mod floyd_steinberg;
+pub mod hybrid;
+pub mod params;
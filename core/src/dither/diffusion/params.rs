@@ -0,0 +1,72 @@
+use crate::dither::ordered::noise::{self, NoiseKind};
+use serde::{Deserialize, Serialize};
+
+/// Parametry wspolne dla wszystkich algorytmow dyfuzji bledu.
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DiffusionParams {
+    /// Czesc bledu przekazywana sasiadom, 0.0..=1.0 (Atkinson rozprowadza ok. 0.75).
+    pub strength: f32,
+    /// Przesuniecie progu 127, ujemne rozjasnia wynik; uzywane w zakresie -127..=127.
+    pub bias: i16,
+    /// Amplituda losowego szumu dodawanego do progu.
+    pub noise: u8,
+    pub seed: u64,
+}
+
+impl Default for DiffusionParams {
+    fn default() -> Self {
+        Self {
+            strength: 1.0,
+            bias: 0,
+            noise: 0,
+            seed: 0,
+        }
+    }
+}
+
+impl DiffusionParams {
+    pub fn threshold_at(&self, x: usize, y: usize) -> i16 {
+        // Struktura bywa wczytana z przepisu, wiec zakres nie jest gwarantowany
+        let mut threshold = 127 + self.bias.clamp(-127, 127);
+        if self.noise > 0 {
+            let n = noise::threshold_at(NoiseKind::WhiteNoise, self.seed, x as u32, y as u32);
+            threshold += ((n as i16 - 128) * self.noise as i16) / 128;
+        }
+        threshold
+    }
+
+    pub fn scale_error(&self, err: i16) -> i16 {
+        let strength = (self.strength.clamp(0.0, 1.0) * 256.0) as i32;
+        ((err as i32 * strength) >> 8) as i16
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bias_is_clamped() {
+        let params = |bias| DiffusionParams {
+            bias,
+            ..DiffusionParams::default()
+        };
+        assert_eq!(params(0).threshold_at(0, 0), 127);
+        assert_eq!(params(100).threshold_at(0, 0), 227);
+        assert_eq!(params(i16::MAX).threshold_at(0, 0), 254);
+        assert_eq!(params(i16::MIN).threshold_at(0, 0), 0);
+    }
+
+    #[test]
+    fn extreme_bias_and_noise_do_not_overflow() {
+        let params = DiffusionParams {
+            bias: i16::MAX,
+            noise: u8::MAX,
+            ..DiffusionParams::default()
+        };
+        for x in 0..64 {
+            assert!((-256..=512).contains(&params.threshold_at(x, x)));
+        }
+    }
+}
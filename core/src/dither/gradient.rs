@@ -0,0 +1,156 @@
+use super::diffusion::hybrid::{diffuse, hybrid_error, hybrid_threshold};
+use super::diffusion::params::DiffusionParams;
+use super::ordered::noise::{self, NoiseKind};
+use super::ordered::threshold_map::ThresholdMap;
+use crate::luma::{LumaModel, to_luma};
+use image::{DynamicImage, Rgb, RgbImage};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Kolor przypisany do pozycji na osi jasnosci (0 = czern, 255 = biel).
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct ColorStop {
+    pub position: u8,
+    pub color: [u8; 3],
+}
+
+impl ColorStop {
+    pub fn new(position: u8, color: [u8; 3]) -> Self {
+        Self { position, color }
+    }
+}
+
+fn sorted_stops(stops: &[ColorStop]) -> Vec<ColorStop> {
+    assert!(!stops.is_empty(), "Gradient needs at least one color stop");
+    let mut stops = stops.to_vec();
+    stops.sort_by_key(|s| s.position);
+    stops
+}
+
+// Zwraca sasiednie przystanki i polozenie luma miedzy nimi w skali 0..=255
+fn segment(stops: &[ColorStop], luma: u32) -> (&ColorStop, &ColorStop, u32) {
+    let first = &stops[0];
+    let last = &stops[stops.len() - 1];
+    if luma <= first.position as u32 {
+        return (first, first, 0);
+    }
+    if luma >= last.position as u32 {
+        return (last, last, 0);
+    }
+
+    let i = stops.partition_point(|s| (s.position as u32) <= luma);
+    let (lo, hi) = (&stops[i - 1], &stops[i]);
+    let span = (hi.position - lo.position) as u32;
+    let t = (luma - lo.position as u32) * 255 / span;
+    (lo, hi, t)
+}
+
+fn dither_ordered<F>(
+    img: &DynamicImage,
+    stops: &[ColorStop],
+    luma: LumaModel,
+    threshold_at: F,
+) -> DynamicImage
+where
+    F: Fn(usize, usize) -> u32 + Sync,
+{
+    let stops = sorted_stops(stops);
+    let rgb = img.to_rgb8();
+    let (width, height) = rgb.dimensions();
+    let mut buffer = rgb.into_raw();
+
+    buffer
+        .par_chunks_exact_mut(3)
+        .enumerate()
+        .for_each(|(i, pixel)| {
+            let x = i % width as usize;
+            let y = i / width as usize;
+
+            let luma = luma.luma([pixel[0], pixel[1], pixel[2]]) as u32;
+
+            let (lo, hi, t) = segment(&stops, luma);
+            if t > threshold_at(x, y) {
+                pixel.copy_from_slice(&hi.color);
+            } else {
+                pixel.copy_from_slice(&lo.color);
+            }
+        });
+
+    let img_out = RgbImage::from_raw(width, height, buffer).unwrap();
+    DynamicImage::ImageRgb8(img_out)
+}
+
+pub fn dither_map(
+    map: &ThresholdMap,
+    img: &DynamicImage,
+    stops: &[ColorStop],
+    luma: LumaModel,
+) -> DynamicImage {
+    dither_ordered(img, stops, luma, |x, y| {
+        let matrix_val = map.value_at(x, y);
+        (matrix_val as u32 * 255) / map.levels
+    })
+}
+
+pub fn dither_noise(
+    kind: NoiseKind,
+    seed: u64,
+    img: &DynamicImage,
+    stops: &[ColorStop],
+    luma: LumaModel,
+) -> DynamicImage {
+    dither_ordered(img, stops, luma, |x, y| {
+        noise::threshold_at(kind, seed, x as u32, y as u32) as u32
+    })
+}
+
+/// Floyd-Steinberg na osi jasnosci: kazdy piksel jest kwantyzowany do blizszego
+/// z dwoch sasiednich przystankow, a blad jasnosci rozchodzi sie dalej.
+pub fn dither_floyd(
+    img: &DynamicImage,
+    stops: &[ColorStop],
+    luma: LumaModel,
+    params: DiffusionParams,
+) -> DynamicImage {
+    dither_hybrid(&ThresholdMap::bayer(2), img, stops, luma, params, 1.0)
+}
+
+pub fn dither_hybrid(
+    map: &ThresholdMap,
+    img: &DynamicImage,
+    stops: &[ColorStop],
+    luma: LumaModel,
+    params: DiffusionParams,
+    blend: f32,
+) -> DynamicImage {
+    let blend = blend.clamp(0.0, 1.0);
+    let stops = sorted_stops(stops);
+    let gray = to_luma(img, luma);
+    let (w, h) = gray.dimensions();
+    let w = w as usize;
+    let h = h as usize;
+
+    let mut err_buffer: Vec<i16> = gray.as_raw().iter().map(|&x| x as i16).collect();
+
+    let mut out_img = RgbImage::new(w as u32, h as u32);
+
+    for y in 0..h {
+        for x in 0..w {
+            let old_val = err_buffer[y * w + x];
+
+            let (lo, hi, t) = segment(&stops, old_val.clamp(0, 255) as u32);
+            let stop = if t as i16 > hybrid_threshold(map, &params, blend, x, y) {
+                hi
+            } else {
+                lo
+            };
+
+            out_img.put_pixel(x as u32, y as u32, Rgb(stop.color));
+
+            let err = hybrid_error(&params, blend, old_val - stop.position as i16);
+            diffuse(&mut err_buffer, w, h, x, y, 1, err);
+        }
+    }
+
+    DynamicImage::ImageRgb8(out_img)
+}
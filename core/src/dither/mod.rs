@@ -1,2 +1,4 @@
+pub mod binarize;
 pub mod diffusion;
+pub mod gradient;
 pub mod ordered;
@@ -1,8 +1,10 @@
 use super::bayer_matrices;
+use super::threshold_map::{ChannelDecorrelation, ThresholdMap};
+use crate::luma::LumaModel;
 use image::{DynamicImage, RgbImage};
 use rayon::prelude::*;
 
-fn get_matrix_slice(n: usize) -> (&'static [u16], usize) {
+pub(crate) fn get_matrix_slice(n: usize) -> (&'static [u16], usize) {
     match n {
         2 => (&bayer_matrices::DITHER_MATRIX_2X2, 2),
         4 => (&bayer_matrices::DITHER_MATRIX_4X4, 4),
@@ -14,15 +16,33 @@ fn get_matrix_slice(n: usize) -> (&'static [u16], usize) {
     }
 }
 
-pub fn dither_colored(n: usize, img: &DynamicImage) -> DynamicImage {
+pub fn dither_colored(
+    n: usize,
+    img: &DynamicImage,
+    decorrelation: ChannelDecorrelation,
+) -> DynamicImage {
+    dither_colored_map(&ThresholdMap::bayer(n), img, decorrelation)
+}
+
+pub fn dither_duoton(
+    n: usize,
+    img: &DynamicImage,
+    low: [u8; 3],
+    high: [u8; 3],
+    luma: LumaModel,
+) -> DynamicImage {
+    dither_duoton_map(&ThresholdMap::bayer(n), img, low, high, luma)
+}
+
+pub fn dither_colored_map(
+    map: &ThresholdMap,
+    img: &DynamicImage,
+    decorrelation: ChannelDecorrelation,
+) -> DynamicImage {
     let rgb = img.to_rgb8();
     let (width, height) = rgb.dimensions();
     let mut buffer = rgb.into_raw();
 
-    let (matrix, size) = get_matrix_slice(n);
-    let mask = (size - 1) as usize;
-    let matrix_len = size * size;
-
     buffer
         .par_chunks_exact_mut(3)
         .enumerate()
@@ -30,30 +50,29 @@ pub fn dither_colored(n: usize, img: &DynamicImage) -> DynamicImage {
             let x = i % width as usize;
             let y = i / width as usize;
 
-            let col = x & mask;
-            let row = y & mask;
-            let matrix_val = matrix[row * size + col];
-
-            let threshold = ((matrix_val as u32 * 255) / matrix_len as u32) as u8;
+            for (c, value) in pixel.iter_mut().enumerate() {
+                let matrix_val = map.channel_value_at(x, y, c, decorrelation);
+                let threshold = ((matrix_val as u32 * 255) / map.levels) as u8;
 
-            pixel[0] = if pixel[0] > threshold { 255 } else { 0 };
-            pixel[1] = if pixel[1] > threshold { 255 } else { 0 };
-            pixel[2] = if pixel[2] > threshold { 255 } else { 0 };
+                *value = if *value > threshold { 255 } else { 0 };
+            }
         });
 
     let img_out = RgbImage::from_raw(width, height, buffer).unwrap();
     DynamicImage::ImageRgb8(img_out)
 }
 
-pub fn dither_duoton(n: usize, img: &DynamicImage, low: [u8; 3], high: [u8; 3]) -> DynamicImage {
+pub fn dither_duoton_map(
+    map: &ThresholdMap,
+    img: &DynamicImage,
+    low: [u8; 3],
+    high: [u8; 3],
+    luma: LumaModel,
+) -> DynamicImage {
     let rgb = img.to_rgb8();
     let (width, height) = rgb.dimensions();
     let mut buffer = rgb.into_raw();
 
-    let (matrix, size) = get_matrix_slice(n);
-    let mask = (size - 1) as usize;
-    let matrix_len = size * size;
-
     buffer
         .par_chunks_exact_mut(3)
         .enumerate()
@@ -61,14 +80,10 @@ pub fn dither_duoton(n: usize, img: &DynamicImage, low: [u8; 3], high: [u8; 3])
             let x = i % width as usize;
             let y = i / width as usize;
 
-            let luma =
-                ((pixel[0] as u32 * 299) + (pixel[1] as u32 * 587) + (pixel[2] as u32 * 114))
-                    / 1000;
+            let luma = luma.luma([pixel[0], pixel[1], pixel[2]]) as u32;
 
-            let col = x & mask;
-            let row = y & mask;
-            let matrix_val = matrix[row * size + col];
-            let threshold = (matrix_val as u32 * 255) / matrix_len as u32; // u32 bo luma jest u32
+            let matrix_val = map.value_at(x, y);
+            let threshold = (matrix_val as u32 * 255) / map.levels; // u32 bo luma jest u32
 
             if luma > threshold {
                 pixel.copy_from_slice(&high);
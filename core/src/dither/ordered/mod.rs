@@ -1,2 +1,4 @@
 pub mod bayer;
 pub mod bayer_matrices;
+pub mod noise;
+pub mod threshold_map;
@@ -0,0 +1,110 @@
+use super::threshold_map::ChannelDecorrelation;
+use crate::luma::LumaModel;
+use image::{DynamicImage, RgbImage};
+use rayon::prelude::*;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum NoiseKind {
+    WhiteNoise,
+    Triangular,
+    InterleavedGradient,
+}
+
+// Hash pozycji piksela zamiast sekwencyjnego RNG, zeby wynik nie zalezal od kolejnosci watkow rayona
+fn hash(x: u32, y: u32, seed: u64) -> u64 {
+    let mut z = seed
+        .wrapping_add((x as u64) << 32 | y as u64)
+        .wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+pub fn threshold_at(kind: NoiseKind, seed: u64, x: u32, y: u32) -> u8 {
+    match kind {
+        NoiseKind::WhiteNoise => (hash(x, y, seed) >> 56) as u8,
+        NoiseKind::Triangular => {
+            let h = hash(x, y, seed);
+            let a = (h >> 56) as u16;
+            let b = ((h >> 48) & 0xFF) as u16;
+            ((a + b + 1) >> 1) as u8
+        }
+        NoiseKind::InterleavedGradient => {
+            // Jimenez 2014, seed przesuwa wzor jak numer klatki
+            let fx = x as f32 + 5.588_238 * (seed % 64) as f32;
+            let fy = y as f32 + 5.588_238 * (seed % 64) as f32;
+            let v = (52.982_918 * (0.067_110_56 * fx + 0.005_837_15 * fy).fract()).fract();
+            (v * 255.0) as u8
+        }
+    }
+}
+
+// Kazdy kanal dostaje wlasny seed, czyli niezalezna mape szumu
+fn channel_seed(seed: u64, channel: usize, decorrelation: ChannelDecorrelation) -> u64 {
+    match decorrelation {
+        ChannelDecorrelation::None => seed,
+        _ => seed.wrapping_add(channel as u64 * 21),
+    }
+}
+
+pub fn dither_colored(
+    kind: NoiseKind,
+    seed: u64,
+    img: &DynamicImage,
+    decorrelation: ChannelDecorrelation,
+) -> DynamicImage {
+    let rgb = img.to_rgb8();
+    let (width, height) = rgb.dimensions();
+    let mut buffer = rgb.into_raw();
+
+    buffer
+        .par_chunks_exact_mut(3)
+        .enumerate()
+        .for_each(|(i, pixel)| {
+            let x = (i % width as usize) as u32;
+            let y = (i / width as usize) as u32;
+
+            for (c, value) in pixel.iter_mut().enumerate() {
+                let threshold = threshold_at(kind, channel_seed(seed, c, decorrelation), x, y);
+
+                *value = if *value > threshold { 255 } else { 0 };
+            }
+        });
+
+    let img_out = RgbImage::from_raw(width, height, buffer).unwrap();
+    DynamicImage::ImageRgb8(img_out)
+}
+
+pub fn dither_duoton(
+    kind: NoiseKind,
+    seed: u64,
+    img: &DynamicImage,
+    low: [u8; 3],
+    high: [u8; 3],
+    luma: LumaModel,
+) -> DynamicImage {
+    let rgb = img.to_rgb8();
+    let (width, height) = rgb.dimensions();
+    let mut buffer = rgb.into_raw();
+
+    buffer
+        .par_chunks_exact_mut(3)
+        .enumerate()
+        .for_each(|(i, pixel)| {
+            let x = (i % width as usize) as u32;
+            let y = (i / width as usize) as u32;
+
+            let luma = luma.luma([pixel[0], pixel[1], pixel[2]]) as u32;
+
+            let threshold = threshold_at(kind, seed, x, y) as u32;
+
+            if luma > threshold {
+                pixel.copy_from_slice(&high);
+            } else {
+                pixel.copy_from_slice(&low);
+            }
+        });
+
+    let img_out = RgbImage::from_raw(width, height, buffer).unwrap();
+    DynamicImage::ImageRgb8(img_out)
+}
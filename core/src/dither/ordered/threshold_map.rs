@@ -0,0 +1,252 @@
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum ThresholdMapError {
+    Io(std::io::Error),
+    Image(image::ImageError),
+    Parse(String),
+    Empty,
+    Ragged {
+        row: usize,
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl fmt::Display for ThresholdMapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "cannot read threshold map: {e}"),
+            Self::Image(e) => write!(f, "cannot decode threshold map image: {e}"),
+            Self::Parse(tok) => write!(f, "invalid number in threshold map: {tok:?}"),
+            Self::Empty => write!(f, "threshold map is empty"),
+            Self::Ragged {
+                row,
+                expected,
+                found,
+            } => write!(
+                f,
+                "threshold map row {row} has {found} values, expected {expected}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ThresholdMapError {}
+
+/// Sposob rozsuniecia progow miedzy kanalami R, G i B w trybie kolorowym.
+#[derive(Debug, PartialEq, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChannelDecorrelation {
+    /// Ten sam prog dla wszystkich kanalow.
+    #[default]
+    None,
+    /// G i B przesuniete o jeden piksel, dla Bayera to prawie przeciwna faza.
+    Offset,
+    /// G obrocone o 90 stopni, B o 180.
+    Rotate,
+    /// G transponowane, B transponowane wzgledem drugiej przekatnej.
+    Transpose,
+}
+
+/// Macierz progow o dowolnym rozmiarze, powtarzana kafelkowo na obrazie.
+/// Prog piksela to `values[..] * 255 / levels`, tak jak dla macierzy Bayera.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ThresholdMap {
+    pub values: Vec<u16>,
+    pub width: usize,
+    pub height: usize,
+    pub levels: u32,
+}
+
+impl ThresholdMap {
+    pub fn bayer(n: usize) -> Self {
+        let (matrix, size) = super::bayer::get_matrix_slice(n);
+        Self {
+            values: matrix.to_vec(),
+            width: size,
+            height: size,
+            levels: (size * size) as u32,
+        }
+    }
+
+    pub fn value_at(&self, x: usize, y: usize) -> u16 {
+        self.values[(y % self.height) * self.width + x % self.width]
+    }
+
+    pub fn channel_value_at(
+        &self,
+        x: usize,
+        y: usize,
+        channel: usize,
+        mode: ChannelDecorrelation,
+    ) -> u16 {
+        let (w, h) = (self.width, self.height);
+        match (mode, channel) {
+            (ChannelDecorrelation::None, _) | (_, 0) => self.value_at(x, y),
+            (ChannelDecorrelation::Offset, 1) => self.value_at(x + 1, y),
+            (ChannelDecorrelation::Offset, _) => self.value_at(x, y + 1),
+            (ChannelDecorrelation::Rotate, 1) => self.values[(h - 1 - x % h) * w + y % w],
+            (ChannelDecorrelation::Rotate, _) => self.value_at(w - 1 - x % w, h - 1 - y % h),
+            (ChannelDecorrelation::Transpose, 1) => self.values[(x % h) * w + y % w],
+            (ChannelDecorrelation::Transpose, _) => {
+                self.values[(h - 1 - x % h) * w + (w - 1 - y % w)]
+            }
+        }
+    }
+
+    /// Jasnosc pikseli (np. PNG z blue noise) uzywana wprost jako prog 0..=255.
+    pub fn from_image(img: &DynamicImage) -> Result<Self, ThresholdMapError> {
+        let gray = img.to_luma8();
+        let (w, h) = gray.dimensions();
+        if w == 0 || h == 0 {
+            return Err(ThresholdMapError::Empty);
+        }
+
+        Ok(Self {
+            values: gray.as_raw().iter().map(|&v| v as u16).collect(),
+            width: w as usize,
+            height: h as usize,
+            levels: 256,
+        })
+    }
+
+    /// Macierz tekstowa: wiersze w liniach, wartosci rozdzielone spacjami, przecinkami lub srednikami.
+    /// Nieujemne liczby calkowite zachowuja swoja skale (levels = max + 1), wiec wklejona macierz
+    /// Bayera daje identyczny wynik; pozostale wartosci sa rozciagane liniowo na 0..=255.
+    pub fn from_text(text: &str) -> Result<Self, ThresholdMapError> {
+        let mut rows: Vec<Vec<f64>> = Vec::new();
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let row = line
+                .split(|c: char| c == ',' || c == ';' || c.is_whitespace())
+                .filter(|tok| !tok.is_empty())
+                .map(|tok| {
+                    tok.parse::<f64>()
+                        .ok()
+                        .filter(|v| v.is_finite())
+                        .ok_or_else(|| ThresholdMapError::Parse(tok.to_string()))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            if let Some(first) = rows.first()
+                && first.len() != row.len()
+            {
+                return Err(ThresholdMapError::Ragged {
+                    row: rows.len() + 1,
+                    expected: first.len(),
+                    found: row.len(),
+                });
+            }
+            rows.push(row);
+        }
+
+        let width = rows.first().map_or(0, |r| r.len());
+        if width == 0 {
+            return Err(ThresholdMapError::Empty);
+        }
+        let height = rows.len();
+        let flat: Vec<f64> = rows.into_iter().flatten().collect();
+
+        let min = flat.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = flat.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let integral =
+            min >= 0.0 && max <= u16::MAX as f64 - 1.0 && flat.iter().all(|v| v.fract() == 0.0);
+
+        let (values, levels) = if integral {
+            (flat.iter().map(|&v| v as u16).collect(), max as u32 + 1)
+        } else {
+            let range = (max - min).max(f64::EPSILON);
+            let values = flat
+                .iter()
+                .map(|&v| (((v - min) / range) * 255.0).round() as u16)
+                .collect();
+            (values, 256)
+        };
+
+        Ok(Self {
+            values,
+            width,
+            height,
+            levels,
+        })
+    }
+
+    /// Pliki `.txt`/`.csv` sa parsowane jako tekst, wszystko inne jako obraz.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ThresholdMapError> {
+        let path = path.as_ref();
+        let is_text = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| matches!(e.to_ascii_lowercase().as_str(), "txt" | "csv" | "tsv"));
+
+        if is_text {
+            let text = std::fs::read_to_string(path).map_err(ThresholdMapError::Io)?;
+            Self::from_text(&text)
+        } else {
+            let img = image::open(path).map_err(ThresholdMapError::Image)?;
+            Self::from_image(&img)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integer_matrix_keeps_its_scale() {
+        let map = ThresholdMap::from_text("0 3\n2 1\n").unwrap();
+        assert_eq!((map.width, map.height, map.levels), (2, 2, 4));
+        assert_eq!(map.values, vec![0, 3, 2, 1]);
+        assert_eq!(map, ThresholdMap::bayer(2));
+    }
+
+    #[test]
+    fn separators_and_comments() {
+        let map = ThresholdMap::from_text("# bayer\n0, 2; 1 # row\n\n3;4,5\n").unwrap();
+        assert_eq!((map.width, map.height), (3, 2));
+        assert_eq!(map.values, vec![0, 2, 1, 3, 4, 5]);
+    }
+
+    #[test]
+    fn fractional_values_are_stretched() {
+        let map = ThresholdMap::from_text("0.0 0.5 1.0").unwrap();
+        assert_eq!(map.levels, 256);
+        assert_eq!(map.values, vec![0, 128, 255]);
+
+        let map = ThresholdMap::from_text("-1 1").unwrap();
+        assert_eq!(map.values, vec![0, 255]);
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        assert!(matches!(
+            ThresholdMap::from_text("# nothing\n\n"),
+            Err(ThresholdMapError::Empty)
+        ));
+        assert!(matches!(
+            ThresholdMap::from_text("1 2 x"),
+            Err(ThresholdMapError::Parse(tok)) if tok == "x"
+        ));
+        assert!(matches!(
+            ThresholdMap::from_text("1 2\n3\n"),
+            Err(ThresholdMapError::Ragged {
+                row: 2,
+                expected: 2,
+                found: 1
+            })
+        ));
+        assert!(matches!(
+            ThresholdMap::from_text("1 inf"),
+            Err(ThresholdMapError::Parse(_))
+        ));
+    }
+}
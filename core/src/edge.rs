@@ -0,0 +1,111 @@
+use crate::convolve::{Border, Kernel, box_kernel, convolve, convolve_separable};
+use crate::luma::{LumaModel, to_luma};
+use image::{DynamicImage, GrayImage, RgbImage};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeMode {
+    #[default]
+    Off,
+    /// Kontrast wokol krawedzi rosnie proporcjonalnie do sily krawedzi.
+    Modulate,
+    /// Piksele na krawedziach sa progowane twardo wzgledem sredniej z otoczenia.
+    Hard,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EdgeParams {
+    pub mode: EdgeMode,
+    /// Minimalna sila krawedzi (0..=255) dla trybu Hard.
+    pub threshold: u8,
+    /// Wzmocnienie kontrastu dla trybu Modulate.
+    pub strength: f32,
+}
+
+impl Default for EdgeParams {
+    fn default() -> Self {
+        Self {
+            mode: EdgeMode::Off,
+            threshold: 64,
+            strength: 2.0,
+        }
+    }
+}
+
+/// Modul gradientu Sobela, przeskalowany do 0..=255.
+pub fn sobel_magnitude(img: &DynamicImage) -> GrayImage {
+    let gray = to_luma(img, LumaModel::Rec601);
+    let (width, height) = gray.dimensions();
+    let (w, h) = (width as usize, height as usize);
+
+    let gx = convolve(gray.as_raw(), w, h, 1, &Kernel::sobel_x(), Border::Clamp);
+    let gy = convolve(gray.as_raw(), w, h, 1, &Kernel::sobel_y(), Border::Clamp);
+    let out = gx
+        .par_iter()
+        .zip(gy.par_iter())
+        .map(|(gx, gy)| ((gx.abs() + gy.abs()) / 4.0).min(255.0) as u8)
+        .collect();
+
+    GrayImage::from_raw(width, height, out).unwrap()
+}
+
+// Srednia 5x5 dla kazdego kanalu
+fn local_mean(rgb: &RgbImage) -> Vec<u8> {
+    let (width, height) = rgb.dimensions();
+    let kernel = box_kernel(2);
+    // Zaokraglenie w dol jak przy dzieleniu calkowitym; sumy sa wielokrotnosciami 1/25
+    convolve_separable(
+        rgb.as_raw(),
+        width as usize,
+        height as usize,
+        3,
+        &kernel,
+        &kernel,
+        Border::Clamp,
+    )
+    .par_iter()
+    .map(|v| (v + 0.01).floor() as u8)
+    .collect()
+}
+
+/// Przygotowuje obraz tak, by dowolny algorytm zachowal ostre krawedzie.
+pub fn emphasize_edges(img: &DynamicImage, params: EdgeParams) -> DynamicImage {
+    if params.mode == EdgeMode::Off {
+        return img.clone();
+    }
+
+    let magnitude = sobel_magnitude(img);
+    let rgb = img.to_rgb8();
+    let (width, height) = rgb.dimensions();
+    let mean = local_mean(&rgb);
+    let mut buffer = rgb.into_raw();
+
+    buffer
+        .par_chunks_exact_mut(3)
+        .zip(mean.par_chunks_exact(3))
+        .zip(magnitude.as_raw().par_iter())
+        .for_each(|((pixel, mean), &mag)| match params.mode {
+            EdgeMode::Off => {}
+            EdgeMode::Modulate => {
+                let gain = 1.0 + params.strength.max(0.0) * mag as f32 / 255.0;
+                for c in 0..3 {
+                    let m = mean[c] as f32;
+                    let v = m + (pixel[c] as f32 - m) * gain;
+                    pixel[c] = v.round().clamp(0.0, 255.0) as u8;
+                }
+            }
+            EdgeMode::Hard => {
+                if mag >= params.threshold {
+                    for c in 0..3 {
+                        pixel[c] = if pixel[c] > mean[c] { 255 } else { 0 };
+                    }
+                }
+            }
+        });
+
+    let img_out = RgbImage::from_raw(width, height, buffer).unwrap();
+    DynamicImage::ImageRgb8(img_out)
+}
@@ -0,0 +1,390 @@
+use super::ExportError;
+use super::indexed::IndexedImage;
+use super::packed::{BitOrder, Layout, PackOptions, Planes, pack_planes};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+const BYTES_PER_LINE: usize = 16;
+
+/// Postac bufora ramki dla firmware: surowe bajty albo tablica w kodzie zrodlowym.
+#[derive(Debug, PartialEq, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceFormat {
+    #[default]
+    Raw,
+    /// `const uint8_t name[] PROGMEM` z definicjami rozmiaru.
+    CHeader,
+    Rust,
+    MicroPython,
+    /// XBM dla Adafruit GFX `drawXBitmap` i u8g2 `drawXBM`: zawsze 1 bpp, wiersze, LSB.
+    Xbm,
+}
+
+impl DeviceFormat {
+    pub const ALL: [Self; 5] = [
+        Self::Raw,
+        Self::CHeader,
+        Self::Rust,
+        Self::MicroPython,
+        Self::Xbm,
+    ];
+
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        Some(match ext.to_ascii_lowercase().as_str() {
+            "raw" | "bin" => Self::Raw,
+            "h" => Self::CHeader,
+            "rs" => Self::Rust,
+            "py" => Self::MicroPython,
+            "xbm" => Self::Xbm,
+            _ => return None,
+        })
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Raw => "bin",
+            Self::CHeader => "h",
+            Self::Rust => "rs",
+            Self::MicroPython => "py",
+            Self::Xbm => "xbm",
+        }
+    }
+
+    /// Opcje faktycznie uzyte przy pakowaniu; XBM narzuca swoj uklad bitow.
+    pub fn pack_options(self, opts: &PackOptions) -> PackOptions {
+        match self {
+            Self::Xbm => PackOptions {
+                bits: 1,
+                bit_order: BitOrder::LsbFirst,
+                layout: Layout::Rows,
+                row_align: 1,
+                planes: Planes::Single,
+                ..*opts
+            },
+            _ => *opts,
+        }
+    }
+
+    /// Plik wynikowy; `name` to nazwa tablicy, zamieniana na poprawny identyfikator.
+    pub fn export(
+        self,
+        img: &IndexedImage,
+        opts: &PackOptions,
+        name: &str,
+    ) -> Result<Vec<u8>, ExportError> {
+        let opts = self.pack_options(opts);
+        let packed = pack_planes(img, &opts)?;
+
+        let name = identifier(name);
+        let planes: Vec<(String, &[u8])> = match opts.planes {
+            Planes::Single => vec![(name.clone(), &packed[0])],
+            Planes::BlackRed => vec![
+                (format!("{name}_black"), &packed[0]),
+                (format!("{name}_red"), &packed[1]),
+            ],
+        };
+        let (width, height) = (img.width, img.height);
+        let text = match self {
+            Self::Raw => return Ok(packed.concat()),
+            Self::CHeader => c_header(&name, width, height, &opts, &planes),
+            Self::Rust => rust_source(&name, width, height, &opts, &planes),
+            Self::MicroPython => micropython(&name, width, height, &opts, &planes),
+            Self::Xbm => xbm(&name, width, height, &packed[0]),
+        };
+        Ok(text.into_bytes())
+    }
+}
+
+// Slowa kluczowe C, Rusta i Pythona, ktorych nie moze nosic tablica
+const KEYWORDS: &[&str] = &[
+    "False", "None", "Self", "True", "and", "as", "assert", "async", "auto", "await", "break",
+    "case", "char", "class", "const", "continue", "crate", "def", "default", "del", "do", "double",
+    "dyn", "elif", "else", "enum", "except", "extern", "false", "finally", "float", "fn", "for",
+    "from", "global", "goto", "if", "impl", "import", "in", "int", "is", "lambda", "let", "long",
+    "loop", "match", "mod", "move", "mut", "nonlocal", "not", "or", "pass", "pub", "raise", "ref",
+    "register", "return", "self", "short", "signed", "sizeof", "static", "struct", "super",
+    "switch", "trait", "true", "try", "type", "typedef", "union", "unsafe", "unsigned", "use",
+    "void", "volatile", "where", "while", "with", "yield",
+];
+
+/// Nazwa zamieniona na identyfikator C/Rust/Pythona; slowa kluczowe dostaja `_` na koncu.
+pub fn identifier(name: &str) -> String {
+    let mut id: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if id.is_empty() || id.starts_with(|c: char| c.is_ascii_digit()) {
+        id.insert(0, '_');
+    }
+    if KEYWORDS.contains(&id.as_str()) {
+        id.push('_');
+    }
+    id
+}
+
+fn describe(width: u32, height: u32, opts: &PackOptions) -> String {
+    let layout = match opts.layout {
+        Layout::Rows => "rows",
+        Layout::Pages => "vertical pages",
+    };
+    let order = match opts.bit_order {
+        BitOrder::MsbFirst => "MSB first",
+        BitOrder::LsbFirst => "LSB first",
+    };
+    let mut text = format!("{width}x{height}, {} bpp, {layout}, {order}", opts.bits);
+    if opts.planes == Planes::BlackRed {
+        text = format!("{width}x{height}, black and red planes, {layout}, {order}");
+    }
+    if opts.invert {
+        text.push_str(", inverted");
+    }
+    text
+}
+
+// Bajty jako `0x..`, po BYTES_PER_LINE w linii z wcieciem
+fn hex_lines(bytes: &[u8], indent: &str) -> String {
+    let mut out = String::new();
+    for line in bytes.chunks(BYTES_PER_LINE) {
+        out.push_str(indent);
+        let values: Vec<String> = line.iter().map(|b| format!("0x{b:02x}")).collect();
+        out.push_str(&values.join(", "));
+        out.push_str(",\n");
+    }
+    out
+}
+
+fn c_header(
+    name: &str,
+    width: u32,
+    height: u32,
+    opts: &PackOptions,
+    planes: &[(String, &[u8])],
+) -> String {
+    let upper = name.to_ascii_uppercase();
+    let mut out = format!("// {}\n", describe(width, height, opts));
+    out.push_str("#pragma once\n\n#include <stdint.h>\n\n");
+    out.push_str("#if defined(__AVR__) || defined(ESP8266)\n#include <avr/pgmspace.h>\n");
+    out.push_str("#elif !defined(PROGMEM)\n#define PROGMEM\n#endif\n\n");
+    let _ = writeln!(out, "#define {upper}_WIDTH {width}");
+    let _ = writeln!(out, "#define {upper}_HEIGHT {height}");
+    for (plane, bytes) in planes {
+        let _ = write!(
+            out,
+            "\nconst uint8_t {plane}[{}] PROGMEM = {{\n{}}};\n",
+            bytes.len(),
+            hex_lines(bytes, "    ")
+        );
+    }
+    out
+}
+
+fn rust_source(
+    name: &str,
+    width: u32,
+    height: u32,
+    opts: &PackOptions,
+    planes: &[(String, &[u8])],
+) -> String {
+    let upper = name.to_ascii_uppercase();
+    let mut out = format!("// {}\n", describe(width, height, opts));
+    let _ = writeln!(out, "pub const {upper}_WIDTH: u32 = {width};");
+    let _ = writeln!(out, "pub const {upper}_HEIGHT: u32 = {height};");
+    for (plane, bytes) in planes {
+        let _ = write!(
+            out,
+            "\npub const {}: [u8; {}] = [\n{}];\n",
+            plane.to_ascii_uppercase(),
+            bytes.len(),
+            hex_lines(bytes, "    ")
+        );
+    }
+    out
+}
+
+// Format modulu framebuf odpowiadajacy ukladowi bajtow, jesli taki istnieje
+fn framebuf_format(opts: &PackOptions) -> Option<&'static str> {
+    Some(match (opts.layout, opts.bits, opts.bit_order) {
+        (Layout::Rows, 1, BitOrder::MsbFirst) => "MONO_HLSB",
+        (Layout::Rows, 1, BitOrder::LsbFirst) => "MONO_HMSB",
+        (Layout::Pages, 1, BitOrder::LsbFirst) => "MONO_VLSB",
+        (Layout::Rows, 2, BitOrder::LsbFirst) => "GS2_HMSB",
+        (Layout::Rows, 4, BitOrder::MsbFirst) => "GS4_HMSB",
+        (Layout::Rows, 8, _) => "GS8",
+        _ => return None,
+    })
+}
+
+fn micropython(
+    name: &str,
+    width: u32,
+    height: u32,
+    opts: &PackOptions,
+    planes: &[(String, &[u8])],
+) -> String {
+    let upper = name.to_ascii_uppercase();
+    let mut out = format!("# {}\n", describe(width, height, opts));
+    let _ = writeln!(out, "{upper}_WIDTH = {width}");
+    let _ = writeln!(out, "{upper}_HEIGHT = {height}");
+    for (plane, bytes) in planes {
+        out.push_str(&format!("\n{plane} = (\n"));
+        for line in bytes.chunks(BYTES_PER_LINE) {
+            let escaped: String = line.iter().map(|b| format!("\\x{b:02x}")).collect();
+            let _ = writeln!(out, "    b'{escaped}'");
+        }
+        out.push_str(")\n");
+    }
+    if opts.row_align <= 1
+        && opts.planes == Planes::Single
+        && let Some(format) = framebuf_format(opts)
+    {
+        let _ = write!(
+            out,
+            "\n# import framebuf\n# fb = framebuf.FrameBuffer(bytearray({name}), {width}, {height}, framebuf.{format})\n"
+        );
+    }
+    out
+}
+
+fn xbm(name: &str, width: u32, height: u32, bytes: &[u8]) -> String {
+    let mut out = format!("#define {name}_width {width}\n#define {name}_height {height}\n");
+    out.push_str("#ifndef PROGMEM\n#define PROGMEM\n#endif\n");
+    let _ = write!(
+        out,
+        "static const unsigned char {name}_bits[] PROGMEM = {{\n{}}};\n",
+        hex_lines(bytes, "   ")
+    );
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::indexed::Indices;
+
+    // 10x2: biale piksele na pozycjach (0,0), (7,0), (8,0) i (9,1)
+    fn image() -> IndexedImage {
+        let mut indices = vec![0; 20];
+        for i in [0, 7, 8, 19] {
+            indices[i] = 1;
+        }
+        IndexedImage {
+            width: 10,
+            height: 2,
+            palette: vec![[0, 0, 0], [255, 255, 255]],
+            indices: Indices::U8(indices),
+        }
+    }
+
+    fn export(format: DeviceFormat, name: &str) -> String {
+        let bytes = format
+            .export(&image(), &PackOptions::default(), name)
+            .unwrap();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn identifiers() {
+        assert_eq!(identifier("logo"), "logo");
+        assert_eq!(identifier("my logo-2.png"), "my_logo_2_png");
+        assert_eq!(identifier("3d"), "_3d");
+        assert_eq!(identifier(""), "_");
+        assert_eq!(identifier("if"), "if_");
+        assert_eq!(identifier("class"), "class_");
+        assert_eq!(identifier("static"), "static_");
+        assert_eq!(identifier("fn"), "fn_");
+        assert_eq!(identifier("None"), "None_");
+        assert_eq!(identifier("iff"), "iff");
+    }
+
+    #[test]
+    fn framebuf_formats() {
+        let format = |layout, bits, bit_order| {
+            framebuf_format(&PackOptions {
+                layout,
+                bits,
+                bit_order,
+                ..Default::default()
+            })
+        };
+        use BitOrder::{LsbFirst, MsbFirst};
+        assert_eq!(format(Layout::Rows, 1, MsbFirst), Some("MONO_HLSB"));
+        assert_eq!(format(Layout::Rows, 1, LsbFirst), Some("MONO_HMSB"));
+        assert_eq!(format(Layout::Pages, 1, LsbFirst), Some("MONO_VLSB"));
+        assert_eq!(format(Layout::Rows, 2, LsbFirst), Some("GS2_HMSB"));
+        assert_eq!(format(Layout::Rows, 4, MsbFirst), Some("GS4_HMSB"));
+        assert_eq!(format(Layout::Rows, 8, LsbFirst), Some("GS8"));
+        assert_eq!(format(Layout::Pages, 1, MsbFirst), None);
+        assert_eq!(format(Layout::Rows, 2, MsbFirst), None);
+    }
+
+    #[test]
+    fn c_header_output() {
+        let expected = concat!(
+            "// 10x2, 1 bpp, rows, MSB first\n",
+            "#pragma once\n",
+            "\n",
+            "#include <stdint.h>\n",
+            "\n",
+            "#if defined(__AVR__) || defined(ESP8266)\n",
+            "#include <avr/pgmspace.h>\n",
+            "#elif !defined(PROGMEM)\n",
+            "#define PROGMEM\n",
+            "#endif\n",
+            "\n",
+            "#define LOGO_WIDTH 10\n",
+            "#define LOGO_HEIGHT 2\n",
+            "\n",
+            "const uint8_t logo[4] PROGMEM = {\n",
+            "    0x81, 0x80, 0x00, 0x40,\n",
+            "};\n",
+        );
+        assert_eq!(export(DeviceFormat::CHeader, "logo"), expected);
+    }
+
+    #[test]
+    fn rust_output() {
+        let expected = concat!(
+            "// 10x2, 1 bpp, rows, MSB first\n",
+            "pub const LOGO_WIDTH: u32 = 10;\n",
+            "pub const LOGO_HEIGHT: u32 = 2;\n",
+            "\n",
+            "pub const LOGO: [u8; 4] = [\n",
+            "    0x81, 0x80, 0x00, 0x40,\n",
+            "];\n",
+        );
+        assert_eq!(export(DeviceFormat::Rust, "logo"), expected);
+    }
+
+    #[test]
+    fn micropython_output() {
+        let expected = concat!(
+            "# 10x2, 1 bpp, rows, MSB first\n",
+            "IF__WIDTH = 10\n",
+            "IF__HEIGHT = 2\n",
+            "\n",
+            "if_ = (\n",
+            "    b'\\x81\\x80\\x00\\x40'\n",
+            ")\n",
+            "\n",
+            "# import framebuf\n",
+            "# fb = framebuf.FrameBuffer(bytearray(if_), 10, 2, framebuf.MONO_HLSB)\n",
+        );
+        assert_eq!(export(DeviceFormat::MicroPython, "if"), expected);
+    }
+
+    #[test]
+    fn xbm_output() {
+        // XBM zawsze pakuje LSB first, niezaleznie od opcji
+        let expected = concat!(
+            "#define logo_width 10\n",
+            "#define logo_height 2\n",
+            "#ifndef PROGMEM\n",
+            "#define PROGMEM\n",
+            "#endif\n",
+            "static const unsigned char logo_bits[] PROGMEM = {\n",
+            "   0x81, 0x01, 0x00, 0x02,\n",
+            "};\n",
+        );
+        assert_eq!(export(DeviceFormat::Xbm, "logo"), expected);
+    }
+}
@@ -0,0 +1,223 @@
+use super::ExportError;
+use super::indexed::IndexedImage;
+use crate::luma::LumaModel;
+use serde::{Deserialize, Serialize};
+
+/// Szerokosc glowicy drukarki 58 mm w punktach.
+pub const PRINTER_58MM: u32 = 384;
+/// Szerokosc glowicy drukarki 80 mm w punktach.
+pub const PRINTER_80MM: u32 = 576;
+
+// Wysokosc pasa dla GS v 0; mniejsze bloki mieszcza sie w buforach tanich drukarek
+const RASTER_BAND: u32 = 255;
+
+#[derive(Debug, PartialEq, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EscPosMode {
+    /// `GS v 0`: obraz rastrowy wiersz po wierszu.
+    #[default]
+    Raster,
+    /// `ESC * 33`: paski po 24 punkty w pionie, dla starszych drukarek bez `GS v 0`.
+    Column,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EscPosOptions {
+    pub mode: EscPosMode,
+    /// Szerokosc glowicy w punktach, zwykle `PRINTER_58MM` lub `PRINTER_80MM`.
+    pub width: u32,
+    /// Puste linie po obrazie, zeby wydruk wyszedl poza obcinarke.
+    pub feed: u8,
+    pub cut: bool,
+}
+
+impl Default for EscPosOptions {
+    fn default() -> Self {
+        Self {
+            mode: EscPosMode::Raster,
+            width: PRINTER_58MM,
+            feed: 3,
+            cut: false,
+        }
+    }
+}
+
+impl EscPosOptions {
+    /// Rozmiar obrazu dopasowany do szerokosci glowicy z zachowaniem proporcji.
+    pub fn fit(&self, width: u32, height: u32) -> (u32, u32) {
+        let scaled = height as f64 * self.width as f64 / width.max(1) as f64;
+        (self.width, (scaled.round() as u32).max(1))
+    }
+}
+
+// Punkt drukowany tam, gdzie kolor palety jest ciemny
+fn dots(img: &IndexedImage) -> Vec<bool> {
+    let dark: Vec<bool> = img
+        .palette
+        .iter()
+        .map(|&c| LumaModel::default().luma(c) < 128)
+        .collect();
+    (0..img.height)
+        .flat_map(|y| (0..img.width).map(move |x| (x, y)))
+        .map(|(x, y)| dark[img.index(x, y)])
+        .collect()
+}
+
+fn raster(out: &mut Vec<u8>, dots: &[bool], width: u32, height: u32) {
+    let row_bytes = width.div_ceil(8);
+    for top in (0..height).step_by(RASTER_BAND as usize) {
+        let rows = RASTER_BAND.min(height - top);
+        out.extend_from_slice(&[0x1d, b'v', b'0', 0]);
+        out.extend_from_slice(&(row_bytes as u16).to_le_bytes());
+        out.extend_from_slice(&(rows as u16).to_le_bytes());
+        for y in top..top + rows {
+            let row = &dots[(y * width) as usize..][..width as usize];
+            for chunk in row.chunks(8) {
+                let byte = chunk
+                    .iter()
+                    .enumerate()
+                    .fold(0u8, |b, (i, &dot)| b | ((dot as u8) << (7 - i)));
+                out.push(byte);
+            }
+        }
+    }
+}
+
+fn column(out: &mut Vec<u8>, dots: &[bool], width: u32, height: u32) {
+    // Odstep linii rowny wysokosci paska, zeby paski sie stykaly
+    out.extend_from_slice(&[0x1b, b'3', 24]);
+    for top in (0..height).step_by(24) {
+        out.extend_from_slice(&[0x1b, b'*', 33]);
+        out.extend_from_slice(&(width as u16).to_le_bytes());
+        for x in 0..width {
+            for byte in 0..3 {
+                let mut value = 0u8;
+                for bit in 0..8 {
+                    let y = top + byte * 8 + bit;
+                    if y < height && dots[(y * width + x) as usize] {
+                        value |= 0x80 >> bit;
+                    }
+                }
+                out.push(value);
+            }
+        }
+        out.push(b'\n');
+    }
+    // Domyslny odstep linii
+    out.extend_from_slice(&[0x1b, b'2']);
+}
+
+/// Polecenia ESC/POS drukujace obraz: inicjalizacja, dane, wysuw i opcjonalne ciecie.
+pub fn encode_escpos(img: &IndexedImage, opts: &EscPosOptions) -> Result<Vec<u8>, ExportError> {
+    if img.width > opts.width || img.width > u16::MAX as u32 {
+        return Err(ExportError::TooLarge {
+            width: img.width,
+            height: img.height,
+        });
+    }
+
+    let dots = dots(img);
+    let mut out = vec![0x1b, b'@'];
+    match opts.mode {
+        EscPosMode::Raster => raster(&mut out, &dots, img.width, img.height),
+        EscPosMode::Column => column(&mut out, &dots, img.width, img.height),
+    }
+    out.extend_from_slice(&[0x1b, b'd', opts.feed]);
+    if opts.cut {
+        // Czesciowe ciecie
+        out.extend_from_slice(&[0x1d, b'V', 1]);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::indexed::Indices;
+
+    // Czarne piksele na przekatnej, reszta biala
+    fn diagonal(width: u32, height: u32) -> IndexedImage {
+        let indices = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x != y) as u8))
+            .collect();
+        IndexedImage {
+            width,
+            height,
+            palette: vec![[0, 0, 0], [255, 255, 255]],
+            indices: Indices::U8(indices),
+        }
+    }
+
+    #[test]
+    fn raster_header_and_rows() {
+        let opts = EscPosOptions {
+            cut: true,
+            ..EscPosOptions::default()
+        };
+        let out = encode_escpos(&diagonal(10, 3), &opts).unwrap();
+        assert_eq!(
+            out,
+            [
+                0x1b, b'@', // inicjalizacja
+                0x1d, b'v', b'0', 0, 2, 0, 3, 0, // 2 bajty na wiersz, 3 wiersze
+                0x80, 0x00, 0x40, 0x00, 0x20, 0x00, // dane
+                0x1b, b'd', 3, // wysuw
+                0x1d, b'V', 1, // ciecie
+            ]
+        );
+    }
+
+    #[test]
+    fn raster_is_split_into_bands() {
+        let out = encode_escpos(&diagonal(8, 300), &EscPosOptions::default()).unwrap();
+        assert_eq!(&out[2..10], [0x1d, b'v', b'0', 0, 1, 0, 255, 0]);
+        let second = 10 + 255;
+        assert_eq!(&out[second..second + 8], [0x1d, b'v', b'0', 0, 1, 0, 45, 0]);
+        assert_eq!(out.len(), second + 8 + 45 + 3);
+    }
+
+    #[test]
+    fn column_header_and_stripes() {
+        let opts = EscPosOptions {
+            mode: EscPosMode::Column,
+            feed: 0,
+            ..EscPosOptions::default()
+        };
+        let out = encode_escpos(&diagonal(2, 25), &opts).unwrap();
+        let stripe = |cols: [[u8; 3]; 2]| {
+            let mut v = vec![0x1b, b'*', 33, 2, 0];
+            v.extend(cols.concat());
+            v.push(b'\n');
+            v
+        };
+        let mut expected = vec![0x1b, b'@', 0x1b, b'3', 24];
+        expected.extend(stripe([[0x80, 0, 0], [0x40, 0, 0]]));
+        // Drugi pasek: tylko wiersz 24, biale piksele
+        expected.extend(stripe([[0; 3], [0; 3]]));
+        expected.extend([0x1b, b'2', 0x1b, b'd', 0]);
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn wider_than_print_head() {
+        let opts = EscPosOptions {
+            width: 8,
+            ..EscPosOptions::default()
+        };
+        assert!(matches!(
+            encode_escpos(&diagonal(9, 1), &opts),
+            Err(ExportError::TooLarge {
+                width: 9,
+                height: 1
+            })
+        ));
+    }
+
+    #[test]
+    fn fit_keeps_aspect_ratio() {
+        let opts = EscPosOptions::default();
+        assert_eq!(opts.fit(768, 200), (PRINTER_58MM, 100));
+        assert_eq!(opts.fit(100, 1), (PRINTER_58MM, 4));
+    }
+}
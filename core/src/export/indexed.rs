@@ -0,0 +1,324 @@
+use super::ExportError;
+use image::{DynamicImage, ImageFormat, RgbImage};
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/// Bufor indeksow; `U16` tylko gdy kolorow jest wiecej niz 256.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Indices {
+    U8(Vec<u8>),
+    U16(Vec<u16>),
+}
+
+/// Wynik ditheringu jako paleta i indeks koloru dla kazdego piksela.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexedImage {
+    pub width: u32,
+    pub height: u32,
+    pub palette: Vec<[u8; 3]>,
+    pub indices: Indices,
+}
+
+impl IndexedImage {
+    /// Indeksuje obraz; kolory z `palette` zachowuja swoje pozycje, pozostale
+    /// dopisywane sa w kolejnosci wystapienia.
+    pub fn from_image(img: &DynamicImage, palette: &[[u8; 3]]) -> Result<Self, ExportError> {
+        Self::with_limit(img, palette, u16::MAX as usize + 1)
+    }
+
+    pub(crate) fn with_limit(
+        img: &DynamicImage,
+        palette: &[[u8; 3]],
+        max: usize,
+    ) -> Result<Self, ExportError> {
+        let rgb = img.to_rgb8();
+        let mut palette = palette.to_vec();
+        let mut lookup: HashMap<[u8; 3], usize> = HashMap::new();
+        for (i, &color) in palette.iter().enumerate() {
+            lookup.entry(color).or_insert(i);
+        }
+
+        let mut indices = Vec::with_capacity((rgb.width() * rgb.height()) as usize);
+        for pixel in rgb.pixels() {
+            let next = palette.len();
+            let index = *lookup.entry(pixel.0).or_insert(next);
+            if index == next {
+                palette.push(pixel.0);
+                if palette.len() > max {
+                    return Err(ExportError::TooManyColors {
+                        colors: palette.len(),
+                        max,
+                    });
+                }
+            }
+            indices.push(index as u16);
+        }
+
+        let indices = if palette.len() <= 256 {
+            Indices::U8(indices.into_iter().map(|i| i as u8).collect())
+        } else {
+            Indices::U16(indices)
+        };
+        Ok(Self {
+            width: rgb.width(),
+            height: rgb.height(),
+            palette,
+            indices,
+        })
+    }
+
+    pub fn index(&self, x: u32, y: u32) -> usize {
+        let i = (y * self.width + x) as usize;
+        match &self.indices {
+            Indices::U8(v) => v[i] as usize,
+            Indices::U16(v) => v[i] as usize,
+        }
+    }
+
+    /// Najmniejsza glebia (1, 2, 4, 8 lub 16 bitow) mieszczaca palete.
+    pub fn bits_per_index(&self) -> u8 {
+        match self.palette.len() {
+            0..=2 => 1,
+            3..=4 => 2,
+            5..=16 => 4,
+            17..=256 => 8,
+            _ => 16,
+        }
+    }
+
+    pub fn to_image(&self) -> DynamicImage {
+        let mut img = RgbImage::new(self.width, self.height);
+        for (x, y, pixel) in img.enumerate_pixels_mut() {
+            pixel.0 = self.palette[self.index(x, y)];
+        }
+        DynamicImage::ImageRgb8(img)
+    }
+
+    fn indices_u8(&self, max: usize) -> Result<&[u8], ExportError> {
+        match &self.indices {
+            Indices::U8(v) if self.palette.len() <= max => Ok(v),
+            _ => Err(ExportError::TooManyColors {
+                colors: self.palette.len(),
+                max,
+            }),
+        }
+    }
+}
+
+// Wiersz indeksow upakowany od najstarszego bitu
+fn pack_row(row: &[u8], bits: u8) -> Vec<u8> {
+    let per_byte = (8 / bits) as usize;
+    row.chunks(per_byte)
+        .map(|chunk| {
+            chunk.iter().enumerate().fold(0u8, |byte, (i, &v)| {
+                byte | v << (8 - bits as usize * (i + 1))
+            })
+        })
+        .collect()
+}
+
+pub fn encode_png(img: &IndexedImage) -> Result<Vec<u8>, ExportError> {
+    let indices = img.indices_u8(256)?;
+    let bits = img.bits_per_index();
+    let depth = match bits {
+        1 => png::BitDepth::One,
+        2 => png::BitDepth::Two,
+        4 => png::BitDepth::Four,
+        _ => png::BitDepth::Eight,
+    };
+
+    let mut out = Vec::new();
+    let mut encoder = png::Encoder::new(&mut out, img.width, img.height);
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(depth);
+    encoder.set_palette(img.palette.concat());
+
+    let data: Vec<u8> = indices
+        .chunks(img.width as usize)
+        .flat_map(|row| pack_row(row, bits))
+        .collect();
+    let mut writer = encoder
+        .write_header()
+        .map_err(|e| ExportError::Encode(e.to_string()))?;
+    writer
+        .write_image_data(&data)
+        .and_then(|_| writer.finish())
+        .map_err(|e| ExportError::Encode(e.to_string()))?;
+    Ok(out)
+}
+
+/// BMP z paleta: 1, 4 lub 8 bitow na piksel (BMP nie ma trybu 2-bitowego).
+pub fn encode_bmp(img: &IndexedImage) -> Result<Vec<u8>, ExportError> {
+    let indices = img.indices_u8(256)?;
+    let bits: u8 = match img.bits_per_index() {
+        1 => 1,
+        2 | 4 => 4,
+        _ => 8,
+    };
+    let too_large = || ExportError::TooLarge {
+        width: img.width,
+        height: img.height,
+    };
+    let width = i32::try_from(img.width).map_err(|_| too_large())?;
+    let height = i32::try_from(img.height).map_err(|_| too_large())?;
+
+    let colors = img.palette.len().max(1) as u32;
+    let stride = (img.width as usize * bits as usize).div_ceil(32) * 4;
+    let offset = 14 + 40 + colors * 4;
+    let data_size = stride * img.height as usize;
+    let file_size = u32::try_from(offset as usize + data_size).map_err(|_| too_large())?;
+
+    let mut out = Vec::with_capacity(file_size as usize);
+    out.extend_from_slice(b"BM");
+    out.extend_from_slice(&file_size.to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(&offset.to_le_bytes());
+
+    out.extend_from_slice(&40u32.to_le_bytes());
+    out.extend_from_slice(&width.to_le_bytes());
+    out.extend_from_slice(&height.to_le_bytes());
+    out.extend_from_slice(&1u16.to_le_bytes());
+    out.extend_from_slice(&(bits as u16).to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(&(data_size as u32).to_le_bytes());
+    // 72 DPI
+    out.extend_from_slice(&2835i32.to_le_bytes());
+    out.extend_from_slice(&2835i32.to_le_bytes());
+    out.extend_from_slice(&colors.to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+
+    for &[r, g, b] in &img.palette {
+        out.extend_from_slice(&[b, g, r, 0]);
+    }
+    if img.palette.is_empty() {
+        out.extend_from_slice(&[0; 4]);
+    }
+
+    // Wiersze od dolu, wyrownane do 4 bajtow
+    for row in indices.chunks(img.width as usize).rev() {
+        let mut packed = pack_row(row, bits);
+        packed.resize(stride, 0);
+        out.extend_from_slice(&packed);
+    }
+    Ok(out)
+}
+
+pub fn encode_gif(img: &IndexedImage) -> Result<Vec<u8>, ExportError> {
+    let indices = img.indices_u8(256)?;
+    let too_large = || ExportError::TooLarge {
+        width: img.width,
+        height: img.height,
+    };
+    let width = u16::try_from(img.width).map_err(|_| too_large())?;
+    let height = u16::try_from(img.height).map_err(|_| too_large())?;
+
+    let encode = |e: gif::EncodingError| ExportError::Encode(e.to_string());
+    let mut encoder =
+        gif::Encoder::new(Vec::new(), width, height, &img.palette.concat()).map_err(encode)?;
+    encoder
+        .write_frame(&gif::Frame {
+            width,
+            height,
+            buffer: Cow::Borrowed(indices),
+            ..Default::default()
+        })
+        .map_err(encode)?;
+    encoder.into_inner().map_err(encode)
+}
+
+/// Zapis z paleta w formatach, ktore ja obsluguja: PNG, BMP i GIF.
+pub fn encode_indexed(img: &IndexedImage, format: ImageFormat) -> Result<Vec<u8>, ExportError> {
+    match format {
+        ImageFormat::Png => encode_png(img),
+        ImageFormat::Bmp => encode_bmp(img),
+        ImageFormat::Gif => encode_gif(img),
+        _ => Err(ExportError::UnsupportedFormat(format)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Obraz z `colors` odcieniami szarosci, kazdy uzyty co najmniej raz
+    fn gray(colors: usize) -> IndexedImage {
+        let width = 7;
+        let height = colors.div_ceil(width) as u32;
+        let palette = (0..colors).map(|i| [i as u8; 3]).collect();
+        let indices = (0..width * height as usize).map(|i| (i % colors) as u8);
+        IndexedImage {
+            width: width as u32,
+            height,
+            palette,
+            indices: Indices::U8(indices.collect()),
+        }
+    }
+
+    #[test]
+    fn png_bit_depth_follows_palette() {
+        for (colors, depth) in [(2, 1), (3, 2), (4, 2), (5, 4), (16, 4), (17, 8), (256, 8)] {
+            let img = gray(colors);
+            let png = encode_png(&img).unwrap();
+            // IHDR: glebia i typ koloru 3 (paleta)
+            assert_eq!((png[24], png[25]), (depth, 3), "{colors} colors");
+            let decoded = image::load_from_memory(&png).unwrap();
+            assert_eq!(
+                decoded.to_rgb8(),
+                img.to_image().to_rgb8(),
+                "{colors} colors"
+            );
+        }
+    }
+
+    #[test]
+    fn bmp_bit_depth_follows_palette() {
+        for (colors, depth) in [(2, 1), (3, 4), (4, 4), (16, 4), (17, 8), (256, 8)] {
+            let img = gray(colors);
+            let bmp = encode_bmp(&img).unwrap();
+            assert_eq!(
+                u16::from_le_bytes([bmp[28], bmp[29]]),
+                depth,
+                "{colors} colors"
+            );
+            let used = u32::from_le_bytes(bmp[46..50].try_into().unwrap());
+            assert_eq!(used, colors as u32);
+            let decoded = image::load_from_memory(&bmp).unwrap();
+            assert_eq!(
+                decoded.to_rgb8(),
+                img.to_image().to_rgb8(),
+                "{colors} colors"
+            );
+        }
+    }
+
+    #[test]
+    fn gif_round_trip() {
+        let img = gray(5);
+        let decoded = image::load_from_memory(&encode_gif(&img).unwrap()).unwrap();
+        assert_eq!(decoded.to_rgb8(), img.to_image().to_rgb8());
+    }
+
+    #[test]
+    fn palette_keeps_given_order() {
+        let rgb = RgbImage::from_fn(3, 1, |x, _| image::Rgb([x as u8 * 100; 3]));
+        let img =
+            IndexedImage::from_image(&DynamicImage::ImageRgb8(rgb), &[[200; 3], [7; 3]]).unwrap();
+        assert_eq!(img.palette, [[200; 3], [7; 3], [0; 3], [100; 3]]);
+        assert_eq!(img.index(0, 0), 2);
+        assert_eq!(img.index(2, 0), 0);
+    }
+
+    #[test]
+    fn too_many_colors_for_palette_formats() {
+        let rgb = RgbImage::from_fn(257, 1, |x, _| image::Rgb([x as u8, (x >> 8) as u8, 0]));
+        let img = IndexedImage::from_image(&DynamicImage::ImageRgb8(rgb), &[]).unwrap();
+        assert!(matches!(img.indices, Indices::U16(_)));
+        assert!(matches!(
+            encode_png(&img),
+            Err(ExportError::TooManyColors {
+                colors: 257,
+                max: 256
+            })
+        ));
+    }
+}
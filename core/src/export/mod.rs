@@ -0,0 +1,56 @@
+pub mod device;
+pub mod escpos;
+pub mod indexed;
+pub mod packed;
+pub mod terminal;
+
+use image::ImageFormat;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum ExportError {
+    Io(std::io::Error),
+    /// Wiecej kolorow niz miesci format lub bufor indeksow.
+    TooManyColors {
+        colors: usize,
+        max: usize,
+    },
+    /// Wymiary poza zakresem formatu.
+    TooLarge {
+        width: u32,
+        height: u32,
+    },
+    UnsupportedFormat(ImageFormat),
+    /// Glebia bitowa, ktorej nie obsluguje pakowanie.
+    UnsupportedDepth(u8),
+    Encode(String),
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "cannot write output: {e}"),
+            Self::TooManyColors { colors, max } => {
+                write!(f, "image has {colors} colors, at most {max} are supported")
+            }
+            Self::TooLarge { width, height } => {
+                write!(f, "image {width}x{height} is too large for this format")
+            }
+            Self::UnsupportedFormat(format) => {
+                write!(f, "indexed export to {format:?} is not supported")
+            }
+            Self::UnsupportedDepth(bits) => {
+                write!(f, "unsupported bit depth {bits}, expected 1, 2, 4 or 8")
+            }
+            Self::Encode(e) => write!(f, "cannot encode image: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ExportError {}
+
+impl From<std::io::Error> for ExportError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
@@ -0,0 +1,330 @@
+use super::ExportError;
+use super::indexed::IndexedImage;
+use crate::luma::LumaModel;
+use serde::{Deserialize, Serialize};
+
+/// Ktory bit bajtu dostaje pierwszy piksel (lewy lub gorny).
+#[derive(Debug, PartialEq, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BitOrder {
+    #[default]
+    MsbFirst,
+    LsbFirst,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Layout {
+    /// Wiersz po wierszu, kolejne piksele wiersza w jednym bajcie.
+    #[default]
+    Rows,
+    /// Strony po kilka wierszy; bajt to pionowy pasek pikseli (SSD1306, SH1106).
+    /// Sterowniki SSD1306 oczekuja `BitOrder::LsbFirst`.
+    Pages,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Planes {
+    /// Jeden bufor z poziomami szarosci.
+    #[default]
+    Single,
+    /// Dwa bufory 1-bitowe, czarny i czerwony, dla trojkolorowego e-papieru.
+    BlackRed,
+}
+
+/// Ustawienia pakowania bufora ramki. Bit ustawiony oznacza jasny piksel
+/// (swiecacy na OLED); w planach czarnym i czerwonym oznacza farbe.
+/// `invert` odwraca wszystkie bity.
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PackOptions {
+    /// Bity na piksel: 1, 2, 4 lub 8.
+    pub bits: u8,
+    pub bit_order: BitOrder,
+    pub layout: Layout,
+    /// Dlugosc wiersza w bajtach zaokraglana do wielokrotnosci tej wartosci.
+    pub row_align: usize,
+    pub invert: bool,
+    pub planes: Planes,
+}
+
+impl Default for PackOptions {
+    fn default() -> Self {
+        Self {
+            bits: 1,
+            bit_order: BitOrder::MsbFirst,
+            layout: Layout::Rows,
+            row_align: 1,
+            invert: false,
+            planes: Planes::Single,
+        }
+    }
+}
+
+impl PackOptions {
+    fn depth(&self) -> Result<u8, ExportError> {
+        let bits = match self.planes {
+            Planes::Single => self.bits,
+            Planes::BlackRed => 1,
+        };
+        match bits {
+            1 | 2 | 4 | 8 => Ok(bits),
+            _ => Err(ExportError::UnsupportedDepth(bits)),
+        }
+    }
+
+    /// Rozmiar jednego planu w bajtach.
+    pub fn plane_size(&self, width: u32, height: u32) -> Result<usize, ExportError> {
+        let bits = self.depth()? as usize;
+        let per_byte = 8 / bits;
+        Ok(match self.layout {
+            Layout::Rows => {
+                let row = (width as usize * bits).div_ceil(8);
+                row.next_multiple_of(self.row_align.max(1)) * height as usize
+            }
+            Layout::Pages => {
+                let row = (width as usize).next_multiple_of(self.row_align.max(1));
+                row * (height as usize).div_ceil(per_byte)
+            }
+        })
+    }
+}
+
+// Czerwony dominuje nad pozostalymi kanalami
+fn is_red([r, g, b]: [u8; 3]) -> bool {
+    r >= 128 && r as u16 > g as u16 + 64 && r as u16 > b as u16 + 64
+}
+
+// Poziom dla kazdego wpisu palety. Gdy kolory mieszcza sie w glebi, decyduje
+// kolejnosc od najciemniejszego, zeby np. dwa ciemne kolory duotonu nie daly tego
+// samego bitu; wiecej kolorow jest mapowanych wedlug jasnosci.
+fn single_levels(palette: &[[u8; 3]], max: u16) -> Vec<u8> {
+    let luma = |c: [u8; 3]| LumaModel::default().luma(c) as u16;
+    if palette.len() < 2 || palette.len() > max as usize + 1 {
+        return palette
+            .iter()
+            .map(|&c| ((luma(c) * max + 127) / 255) as u8)
+            .collect();
+    }
+
+    let mut order: Vec<usize> = (0..palette.len()).collect();
+    order.sort_by_key(|&i| luma(palette[i]));
+    let last = palette.len() as u16 - 1;
+    let mut levels = vec![0u8; palette.len()];
+    for (rank, &i) in order.iter().enumerate() {
+        levels[i] = ((rank as u16 * max + last / 2) / last) as u8;
+    }
+    levels
+}
+
+fn pack_plane(
+    img: &IndexedImage,
+    levels: &[u8],
+    bits: u8,
+    opts: &PackOptions,
+    size: usize,
+) -> Vec<u8> {
+    let (width, height) = (img.width as usize, img.height as usize);
+    let per_byte = 8 / bits as usize;
+    let mask = ((1u16 << bits) - 1) as u8;
+    let value = |x: usize, y: usize| {
+        let v = levels[img.index(x as u32, y as u32)];
+        if opts.invert { !v & mask } else { v }
+    };
+    // Pozycja piksela w bajcie liczona od pierwszego piksela
+    let shift = |slot: usize| match opts.bit_order {
+        BitOrder::MsbFirst => 8 - bits as usize * (slot + 1),
+        BitOrder::LsbFirst => bits as usize * slot,
+    };
+    let mut out = vec![0u8; size];
+
+    match opts.layout {
+        Layout::Rows => {
+            let stride = out.len() / height.max(1);
+            for y in 0..height {
+                for x in 0..width {
+                    out[y * stride + x / per_byte] |= value(x, y) << shift(x % per_byte);
+                }
+            }
+        }
+        Layout::Pages => {
+            let stride = width.next_multiple_of(opts.row_align.max(1));
+            for y in 0..height {
+                for x in 0..width {
+                    out[(y / per_byte) * stride + x] |= value(x, y) << shift(y % per_byte);
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Bufory ramki gotowe do wyslania do sterownika; przy `Planes::BlackRed` najpierw
+/// plan czarny, potem czerwony.
+pub fn pack_planes(img: &IndexedImage, opts: &PackOptions) -> Result<Vec<Vec<u8>>, ExportError> {
+    let bits = opts.depth()?;
+    let max = (1u16 << bits) - 1;
+    let size = opts.plane_size(img.width, img.height)?;
+
+    Ok(match opts.planes {
+        Planes::Single => {
+            let levels = single_levels(&img.palette, max);
+            vec![pack_plane(img, &levels, bits, opts, size)]
+        }
+        Planes::BlackRed => {
+            let black: Vec<u8> = img
+                .palette
+                .iter()
+                .map(|&c| (!is_red(c) && LumaModel::default().luma(c) < 128) as u8)
+                .collect();
+            let red: Vec<u8> = img.palette.iter().map(|&c| is_red(c) as u8).collect();
+            vec![
+                pack_plane(img, &black, bits, opts, size),
+                pack_plane(img, &red, bits, opts, size),
+            ]
+        }
+    })
+}
+
+/// Surowy plik binarny: wszystkie plany jeden po drugim.
+pub fn pack(img: &IndexedImage, opts: &PackOptions) -> Result<Vec<u8>, ExportError> {
+    Ok(pack_planes(img, opts)?.concat())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::indexed::Indices;
+
+    const BLACK: [u8; 3] = [0, 0, 0];
+    const WHITE: [u8; 3] = [255, 255, 255];
+
+    fn image(width: u32, palette: &[[u8; 3]], indices: &[u8]) -> IndexedImage {
+        IndexedImage {
+            width,
+            height: indices.len() as u32 / width,
+            palette: palette.to_vec(),
+            indices: Indices::U8(indices.to_vec()),
+        }
+    }
+
+    // 10x2: biale piksele na pozycjach (0,0), (7,0), (8,0) i (9,1)
+    fn rows() -> IndexedImage {
+        let mut indices = vec![0; 20];
+        for i in [0, 7, 8, 19] {
+            indices[i] = 1;
+        }
+        image(10, &[BLACK, WHITE], &indices)
+    }
+
+    // 3x10: biale piksele na pozycjach (0,0), (0,7), (1,8) i (2,9)
+    fn columns() -> IndexedImage {
+        let mut indices = vec![0; 30];
+        for (x, y) in [(0, 0), (0, 7), (1, 8), (2, 9)] {
+            indices[y * 3 + x] = 1;
+        }
+        image(3, &[BLACK, WHITE], &indices)
+    }
+
+    #[test]
+    fn rows_msb_first() {
+        let packed = pack(&rows(), &PackOptions::default()).unwrap();
+        assert_eq!(packed, [0x81, 0x80, 0x00, 0x40]);
+    }
+
+    #[test]
+    fn rows_lsb_first() {
+        let opts = PackOptions {
+            bit_order: BitOrder::LsbFirst,
+            ..PackOptions::default()
+        };
+        assert_eq!(pack(&rows(), &opts).unwrap(), [0x81, 0x01, 0x00, 0x02]);
+    }
+
+    #[test]
+    fn rows_aligned_and_inverted() {
+        let opts = PackOptions {
+            row_align: 4,
+            invert: true,
+            ..PackOptions::default()
+        };
+        assert_eq!(opts.plane_size(10, 2).unwrap(), 8);
+        // Bity dopelnienia zostaja zerowe takze po odwroceniu
+        assert_eq!(
+            pack(&rows(), &opts).unwrap(),
+            [0x7e, 0x40, 0, 0, 0xff, 0x80, 0, 0]
+        );
+    }
+
+    #[test]
+    fn pages_lsb_first() {
+        let opts = PackOptions {
+            layout: Layout::Pages,
+            bit_order: BitOrder::LsbFirst,
+            ..PackOptions::default()
+        };
+        assert_eq!(opts.plane_size(3, 10).unwrap(), 6);
+        assert_eq!(
+            pack(&columns(), &opts).unwrap(),
+            [0x81, 0x00, 0x00, 0x00, 0x01, 0x02]
+        );
+    }
+
+    #[test]
+    fn pages_msb_first() {
+        let opts = PackOptions {
+            layout: Layout::Pages,
+            ..PackOptions::default()
+        };
+        assert_eq!(
+            pack(&columns(), &opts).unwrap(),
+            [0x81, 0x00, 0x00, 0x00, 0x80, 0x40]
+        );
+    }
+
+    #[test]
+    fn two_bits_per_pixel() {
+        let gray = image(4, &[BLACK, [85; 3], [170; 3], WHITE], &[0, 1, 2, 3]);
+        let msb = PackOptions {
+            bits: 2,
+            ..PackOptions::default()
+        };
+        assert_eq!(pack(&gray, &msb).unwrap(), [0x1b]);
+        let lsb = PackOptions {
+            bit_order: BitOrder::LsbFirst,
+            ..msb
+        };
+        assert_eq!(pack(&gray, &lsb).unwrap(), [0xe4]);
+    }
+
+    #[test]
+    fn dark_palette_packs_by_rank() {
+        // Granat i bordo sa ciemne, ale bordo jasniejsze
+        let duoton = image(4, &[[128, 0, 0], [0, 0, 128]], &[0, 1, 1, 0]);
+        assert_eq!(pack(&duoton, &PackOptions::default()).unwrap(), [0x90]);
+    }
+
+    #[test]
+    fn black_and_red_planes() {
+        let img = image(8, &[WHITE, BLACK, [255, 0, 0]], &[0, 1, 2, 1, 0, 0, 2, 0]);
+        let opts = PackOptions {
+            planes: Planes::BlackRed,
+            ..PackOptions::default()
+        };
+        assert_eq!(pack_planes(&img, &opts).unwrap(), [vec![0x50], vec![0x22]]);
+        assert_eq!(pack(&img, &opts).unwrap(), [0x50, 0x22]);
+    }
+
+    #[test]
+    fn rejects_unsupported_depth() {
+        let opts = PackOptions {
+            bits: 3,
+            ..PackOptions::default()
+        };
+        assert!(matches!(
+            pack(&rows(), &opts),
+            Err(ExportError::UnsupportedDepth(3))
+        ));
+    }
+}
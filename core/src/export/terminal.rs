@@ -0,0 +1,272 @@
+use super::ExportError;
+use super::indexed::IndexedImage;
+use crate::luma::LumaModel;
+use image::DynamicImage;
+use image::imageops::FilterType;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fmt::Write;
+
+/// Podglad w terminalu: grafika Sixel, polbloki ANSI w 24-bitowym kolorze albo
+/// znaki Braille'a dla obrazow 1-bitowych.
+#[derive(Debug, PartialEq, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PreviewFormat {
+    /// Pelna rozdzielczosc; wymaga terminala z obsluga Sixel (xterm -ti vt340, foot, WezTerm).
+    Sixel,
+    /// `▀` z kolorem tekstu dla gornego i tla dla dolnego piksela.
+    #[default]
+    Ansi,
+    /// Komorka 2x4 punkty; punkt podniesiony tam, gdzie piksel jest jasny.
+    Braille,
+}
+
+impl PreviewFormat {
+    /// Pikseli obrazu na jedna kolumne terminala.
+    pub fn pixels_per_column(self) -> u32 {
+        match self {
+            Self::Sixel | Self::Ansi => 1,
+            Self::Braille => 2,
+        }
+    }
+
+    /// Tekst do wypisania w terminalu; `palette` jak w `IndexedImage::from_image`.
+    /// `columns` ogranicza szerokosc polblokow i Braille'a, Sixel zawsze ma pelna
+    /// rozdzielczosc.
+    pub fn render(
+        self,
+        img: &DynamicImage,
+        palette: &[[u8; 3]],
+        columns: Option<u32>,
+    ) -> Result<String, ExportError> {
+        let max = columns.map(|c| c.max(1) * self.pixels_per_column());
+        let img = match max {
+            Some(max) if self != Self::Sixel && img.width() > max => {
+                // Najblizszy sasiad, zeby nie pojawily sie kolory spoza palety
+                let height = (img.height() as u64 * max as u64).div_ceil(img.width() as u64);
+                Cow::Owned(img.resize_exact(max, (height as u32).max(1), FilterType::Nearest))
+            }
+            _ => Cow::Borrowed(img),
+        };
+        let indexed = IndexedImage::from_image(&img, palette)?;
+        match self {
+            Self::Sixel => encode_sixel(&indexed),
+            Self::Ansi => Ok(encode_ansi(&indexed)),
+            Self::Braille => encode_braille(&indexed),
+        }
+    }
+}
+
+// Powtorzenie znaku sixel, z kompresja `!n` dla dluzszych serii
+fn sixel_run(out: &mut String, value: u8, count: usize) {
+    let c = (0x3f + value) as char;
+    if count > 3 {
+        let _ = write!(out, "!{count}{c}");
+    } else {
+        out.extend(std::iter::repeat_n(c, count));
+    }
+}
+
+/// Sekwencja DCS Sixel z paleta obrazu (do 256 kolorow).
+pub fn encode_sixel(img: &IndexedImage) -> Result<String, ExportError> {
+    if img.palette.len() > 256 {
+        return Err(ExportError::TooManyColors {
+            colors: img.palette.len(),
+            max: 256,
+        });
+    }
+
+    // P2=1: niezapisane piksele zostaja bez zmian zamiast tla
+    let mut out = format!("\x1bP0;1;0q\"1;1;{};{}", img.width, img.height);
+    for (i, &[r, g, b]) in img.palette.iter().enumerate() {
+        // Skladowe w procentach
+        let p = |v: u8| (v as u32 * 100 + 127) / 255;
+        let _ = write!(out, "#{i};2;{};{};{}", p(r), p(g), p(b));
+    }
+
+    let width = img.width as usize;
+    let mut band = vec![0u8; width];
+    for top in (0..img.height).step_by(6) {
+        let rows = 6.min(img.height - top);
+        let mut used = vec![false; img.palette.len()];
+        for y in top..top + rows {
+            for x in 0..img.width {
+                used[img.index(x, y)] = true;
+            }
+        }
+
+        let mut first = true;
+        for color in (0..img.palette.len()).filter(|&c| used[c]) {
+            for (x, value) in band.iter_mut().enumerate() {
+                *value = (0..rows)
+                    .filter(|&dy| img.index(x as u32, top + dy) == color)
+                    .fold(0, |v, dy| v | 1 << dy);
+            }
+            // Powrot na poczatek pasa przed kolejnym kolorem
+            if !first {
+                out.push('$');
+            }
+            first = false;
+            let _ = write!(out, "#{color}");
+
+            // Puste miejsca na koncu pasa mozna pominac
+            let len = band.iter().rposition(|&v| v != 0).map_or(0, |i| i + 1);
+            let mut x = 0;
+            while x < len {
+                let value = band[x];
+                let count = band[x..len].iter().take_while(|&&v| v == value).count();
+                sixel_run(&mut out, value, count);
+                x += count;
+            }
+        }
+        // Nastepny pas szesciu wierszy
+        out.push('-');
+    }
+    out.push_str("\x1b\\");
+    Ok(out)
+}
+
+/// Polbloki `▀` z kolorem 24-bitowym: dwa wiersze obrazu na linie terminala.
+pub fn encode_ansi(img: &IndexedImage) -> String {
+    let mut out = String::new();
+    for top in (0..img.height).step_by(2) {
+        let mut last: Option<(usize, Option<usize>)> = None;
+        for x in 0..img.width {
+            let upper = img.index(x, top);
+            let lower = (top + 1 < img.height).then(|| img.index(x, top + 1));
+            // Kolory wysylane tylko przy zmianie
+            if last != Some((upper, lower)) {
+                let [r, g, b] = img.palette[upper];
+                let _ = write!(out, "\x1b[38;2;{r};{g};{b}m");
+                match lower {
+                    Some(lower) => {
+                        let [r, g, b] = img.palette[lower];
+                        let _ = write!(out, "\x1b[48;2;{r};{g};{b}m");
+                    }
+                    // Ostatni wiersz przy nieparzystej wysokosci: tlo terminala
+                    None => out.push_str("\x1b[49m"),
+                }
+                last = Some((upper, lower));
+            }
+            out.push('▀');
+        }
+        out.push_str("\x1b[0m\n");
+    }
+    out
+}
+
+/// Znaki Braille'a dla obrazu o co najwyzej dwoch kolorach.
+pub fn encode_braille(img: &IndexedImage) -> Result<String, ExportError> {
+    if img.palette.len() > 2 {
+        return Err(ExportError::TooManyColors {
+            colors: img.palette.len(),
+            max: 2,
+        });
+    }
+    let light: Vec<bool> = img
+        .palette
+        .iter()
+        .map(|&c| LumaModel::default().luma(c) >= 128)
+        .collect();
+
+    // Bity punktow w kolejnosci Unicode: kolumna lewa 1, 2, 3, 7; prawa 4, 5, 6, 8
+    const DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+    let mut out = String::new();
+    for top in (0..img.height).step_by(4) {
+        for left in (0..img.width).step_by(2) {
+            let mut cell = 0;
+            for (dy, row) in DOTS.iter().enumerate() {
+                for (dx, bit) in row.iter().enumerate() {
+                    let (x, y) = (left + dx as u32, top + dy as u32);
+                    if x < img.width && y < img.height && light[img.index(x, y)] {
+                        cell |= bit;
+                    }
+                }
+            }
+            out.push(char::from_u32(0x2800 + cell).unwrap_or(' '));
+        }
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::indexed::Indices;
+
+    const BLACK: [u8; 3] = [0, 0, 0];
+    const WHITE: [u8; 3] = [255, 255, 255];
+
+    fn image(width: u32, palette: &[[u8; 3]], indices: &[u8]) -> IndexedImage {
+        IndexedImage {
+            width,
+            height: indices.len() as u32 / width,
+            palette: palette.to_vec(),
+            indices: Indices::U8(indices.to_vec()),
+        }
+    }
+
+    #[test]
+    fn sixel_header_palette_and_runs() {
+        let img = image(6, &[BLACK, [255, 0, 128]], &[0, 0, 0, 0, 0, 1]);
+        assert_eq!(
+            encode_sixel(&img).unwrap(),
+            "\x1bP0;1;0q\"1;1;6;1#0;2;0;0;0#1;2;100;0;50#0!5@$#1!5?@-\x1b\\"
+        );
+    }
+
+    #[test]
+    fn sixel_bands_and_short_runs() {
+        // Drugi pas ma jeden wiersz; nieuzyty kolor jest tylko w palecie
+        let img = image(3, &[BLACK, WHITE], &[0; 21]);
+        assert_eq!(
+            encode_sixel(&img).unwrap(),
+            "\x1bP0;1;0q\"1;1;3;7#0;2;0;0;0#1;2;100;100;100#0~~~-#0@@@-\x1b\\"
+        );
+    }
+
+    #[test]
+    fn ansi_reuses_colors_and_ends_odd_rows() {
+        let img = image(3, &[BLACK, WHITE], &[0, 0, 1, 1, 1, 1, 0, 1, 1]);
+        let expected = concat!(
+            "\x1b[38;2;0;0;0m\x1b[48;2;255;255;255m▀▀",
+            "\x1b[38;2;255;255;255m\x1b[48;2;255;255;255m▀\x1b[0m\n",
+            "\x1b[38;2;0;0;0m\x1b[49m▀",
+            "\x1b[38;2;255;255;255m\x1b[49m▀▀\x1b[0m\n",
+        );
+        assert_eq!(encode_ansi(&img), expected);
+    }
+
+    #[test]
+    fn braille_dots() {
+        // Jasne piksele: (0,0), (1,0), (1,2) i (0,3)
+        let img = image(2, &[BLACK, WHITE], &[1, 1, 0, 0, 0, 1, 1, 0]);
+        assert_eq!(encode_braille(&img).unwrap(), "\u{2869}\n");
+
+        // Jasnosc liczona z koloru, nie z indeksu; niepelna komorka na krawedzi
+        let img = image(3, &[WHITE, BLACK], &[0, 0, 0]);
+        assert_eq!(encode_braille(&img).unwrap(), "\u{2809}\u{2801}\n");
+    }
+
+    #[test]
+    fn rejects_too_many_colors() {
+        let img = image(3, &[BLACK, WHITE, [255, 0, 0]], &[0, 1, 2]);
+        assert!(matches!(
+            encode_braille(&img),
+            Err(ExportError::TooManyColors { colors: 3, max: 2 })
+        ));
+
+        let palette: Vec<[u8; 3]> = (0..257)
+            .map(|i| [(i % 256) as u8, (i / 256) as u8, 0])
+            .collect();
+        let img = image(1, &palette, &[0]);
+        assert!(matches!(
+            encode_sixel(&img),
+            Err(ExportError::TooManyColors {
+                colors: 257,
+                max: 256
+            })
+        ));
+    }
+}
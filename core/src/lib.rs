@@ -1,6 +1,49 @@
+pub mod adjust;
+pub mod color_space;
+pub mod convolve;
 pub mod dither;
+pub mod edge;
+pub mod export;
+pub mod luma;
+pub mod pipeline;
+pub mod recipe;
+pub mod resize;
 
+pub use adjust::filters::{Denoise, Sharpen};
+pub use adjust::histogram::{Equalization, histogram};
+pub use adjust::{Adjustments, adjust};
+pub use color_space::{DitherSpace, dither_in_space};
+pub use convolve::{Border, Kernel, convolve, convolve_separable, gaussian_blur};
+pub use dither::binarize::Binarization;
+pub use dither::binarize::dither_duoton as binarize_duoton;
 pub use dither::diffusion::floyd_steinberg::dither_colored as floyd_dither_colored;
 pub use dither::diffusion::floyd_steinberg::dither_duoton as floyd_dither_duoton;
+pub use dither::diffusion::hybrid::dither_colored as hybrid_dither_colored;
+pub use dither::diffusion::hybrid::dither_duoton as hybrid_dither_duoton;
+pub use dither::diffusion::params::DiffusionParams;
+pub use dither::gradient::ColorStop;
+pub use dither::gradient::dither_floyd as gradient_dither_floyd;
+pub use dither::gradient::dither_hybrid as gradient_dither_hybrid;
+pub use dither::gradient::dither_map as gradient_dither_map;
+pub use dither::gradient::dither_noise as gradient_dither_noise;
 pub use dither::ordered::bayer::dither_colored as bayer_dither_colored;
+pub use dither::ordered::bayer::dither_colored_map as map_dither_colored;
 pub use dither::ordered::bayer::dither_duoton as bayer_dither_duoton;
+pub use dither::ordered::bayer::dither_duoton_map as map_dither_duoton;
+pub use dither::ordered::noise::NoiseKind;
+pub use dither::ordered::noise::dither_colored as noise_dither_colored;
+pub use dither::ordered::noise::dither_duoton as noise_dither_duoton;
+pub use dither::ordered::threshold_map::{ChannelDecorrelation, ThresholdMap, ThresholdMapError};
+pub use edge::{EdgeMode, EdgeParams, emphasize_edges};
+pub use export::ExportError;
+pub use export::device::DeviceFormat;
+pub use export::escpos::{EscPosMode, EscPosOptions, PRINTER_58MM, PRINTER_80MM, encode_escpos};
+pub use export::indexed::{IndexedImage, Indices, encode_indexed};
+pub use export::packed::{BitOrder, Layout, PackOptions, Planes, pack, pack_planes};
+pub use export::terminal::{PreviewFormat, encode_ansi, encode_braille, encode_sixel};
+pub use luma::{LumaModel, grayscale, to_luma};
+pub use pipeline::{Algorithm, DitherMode, DitherSettings};
+pub use recipe::metadata::{encode_with_recipe, extract_recipe, read_recipe, save_with_recipe};
+pub use recipe::project::{ImageSource, Project, ViewState};
+pub use recipe::{RECIPE_VERSION, Recipe, RecipeError};
+pub use resize::{Crop, ResizeFilter, crop, resize, upscale_nearest};
@@ -0,0 +1,85 @@
+use image::{DynamicImage, GrayImage};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::sync::LazyLock;
+
+#[derive(Debug, PartialEq, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LumaModel {
+    #[default]
+    Rec601,
+    Rec709,
+    /// Luminancja Rec.709 liczona w swietle liniowym i zakodowana z powrotem do sRGB.
+    LinearLight,
+    Red,
+    Green,
+    Blue,
+    Custom([f32; 3]),
+}
+
+static SRGB_TO_LINEAR: LazyLock<[f32; 256]> =
+    LazyLock::new(|| std::array::from_fn(|i| srgb_to_linear(i as f32 / 255.0)));
+
+pub fn srgb_to_linear(v: f32) -> f32 {
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub fn linear_to_srgb(v: f32) -> f32 {
+    if v <= 0.003_130_8 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+pub fn srgb_u8_to_linear(v: u8) -> f32 {
+    SRGB_TO_LINEAR[v as usize]
+}
+
+impl LumaModel {
+    pub fn luma(&self, rgb: [u8; 3]) -> u8 {
+        let [r, g, b] = rgb.map(|c| c as u32);
+        match self {
+            Self::Rec601 => ((r * 299 + g * 587 + b * 114) / 1000) as u8,
+            Self::Rec709 => ((r * 2126 + g * 7152 + b * 722) / 10000) as u8,
+            Self::LinearLight => {
+                let y = 0.2126 * srgb_u8_to_linear(rgb[0])
+                    + 0.7152 * srgb_u8_to_linear(rgb[1])
+                    + 0.0722 * srgb_u8_to_linear(rgb[2]);
+                (linear_to_srgb(y) * 255.0).round().clamp(0.0, 255.0) as u8
+            }
+            Self::Red => rgb[0],
+            Self::Green => rgb[1],
+            Self::Blue => rgb[2],
+            Self::Custom(w) => {
+                let sum = w[0] + w[1] + w[2];
+                if sum.abs() < f32::EPSILON {
+                    return 0;
+                }
+                let y = (w[0] * r as f32 + w[1] * g as f32 + w[2] * b as f32) / sum;
+                y.round().clamp(0.0, 255.0) as u8
+            }
+        }
+    }
+}
+
+pub fn to_luma(img: &DynamicImage, model: LumaModel) -> GrayImage {
+    let rgb = img.to_rgb8();
+    let (width, height) = rgb.dimensions();
+
+    let buffer: Vec<u8> = rgb
+        .as_raw()
+        .par_chunks_exact(3)
+        .map(|p| model.luma([p[0], p[1], p[2]]))
+        .collect();
+
+    GrayImage::from_raw(width, height, buffer).unwrap()
+}
+
+pub fn grayscale(img: &DynamicImage, model: LumaModel) -> DynamicImage {
+    DynamicImage::ImageLuma8(to_luma(img, model))
+}
@@ -0,0 +1,455 @@
+use crate::adjust::filters::Denoise;
+use crate::adjust::histogram::Equalization;
+use crate::adjust::{Adjustments, adjust};
+use crate::color_space::{DitherSpace, dither_in_space};
+use crate::dither::binarize::{self, Binarization};
+use crate::dither::diffusion::params::DiffusionParams;
+use crate::dither::diffusion::{floyd_steinberg, hybrid};
+use crate::dither::gradient::{self, ColorStop};
+use crate::dither::ordered::bayer;
+use crate::dither::ordered::noise::{self, NoiseKind};
+use crate::dither::ordered::threshold_map::{ChannelDecorrelation, ThresholdMap};
+use crate::edge::{EdgeParams, emphasize_edges};
+use crate::export::ExportError;
+use crate::export::indexed::IndexedImage;
+use crate::luma::{LumaModel, grayscale};
+use crate::recipe::RecipeError;
+use crate::resize::{Crop, ResizeFilter, crop, resize, upscale_nearest};
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+// Granice dla ustawien z plikow, z zapasem ponad zakresy w GUI i CLI
+const MAX_WINDOW_RADIUS: u32 = 256;
+const MAX_DENOISE_RADIUS: u32 = 16;
+const MAX_SHARPEN_RADIUS: f32 = 32.0;
+const MAX_CLAHE_TILES: u32 = 64;
+const MAX_PIXEL_SCALE: u32 = 64;
+const MAX_DIMENSION: u32 = 16384;
+
+#[derive(Debug, PartialEq, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Algorithm {
+    #[default]
+    Original,
+    Bayer,
+    WhiteNoise,
+    TriangularNoise,
+    InterleavedGradient,
+    CustomMap,
+    Floyd,
+    Hybrid,
+    Binarize,
+}
+
+impl Algorithm {
+    pub fn is_diffusion(self) -> bool {
+        matches!(self, Self::Floyd | Self::Hybrid)
+    }
+
+    pub fn is_ordered(self) -> bool {
+        !matches!(self, Self::Original | Self::Binarize) && !self.is_diffusion()
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DitherMode {
+    #[default]
+    Grayscale,
+    Colored,
+    /// Duoton lub gradient z `color_stops`.
+    Duoton,
+}
+
+/// Wszystkie ustawienia potrzebne do przetworzenia obrazu od wejscia do wyniku.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DitherSettings {
+    pub algorithm: Algorithm,
+    pub mode: DitherMode,
+    /// Wykladnik rozmiaru macierzy Bayera (2^n).
+    pub bayer_size: usize,
+    pub noise_seed: u64,
+    pub diffusion: DiffusionParams,
+    pub hybrid_blend: f32,
+    pub hybrid_custom_map: bool,
+    pub binarization: Binarization,
+    pub custom_map: Option<ThresholdMap>,
+
+    pub color_stops: Vec<ColorStop>,
+    pub luma_model: LumaModel,
+    pub channel_decorrelation: ChannelDecorrelation,
+    pub dither_space: DitherSpace,
+    pub edge: EdgeParams,
+    pub adjustments: Adjustments,
+
+    /// Wycinek zrodla brany przed skalowaniem. Dotyczy jednego obrazu, wiec nie
+    /// trafia do przepisow; zapisuje go tylko `Project`.
+    #[serde(skip)]
+    pub crop: Option<Crop>,
+    /// Rozmiar docelowy; 0 zostawia rozmiar wejscia.
+    pub target_width: u32,
+    pub target_height: u32,
+    pub resize_filter: ResizeFilter,
+    pub gamma_correct_resize: bool,
+    pub pixel_scale: u32,
+}
+
+impl Default for DitherSettings {
+    fn default() -> Self {
+        Self {
+            algorithm: Algorithm::Original,
+            mode: DitherMode::Grayscale,
+            bayer_size: 2,
+            noise_seed: 0,
+            diffusion: DiffusionParams::default(),
+            hybrid_blend: 0.5,
+            hybrid_custom_map: false,
+            binarization: Binarization::default(),
+            custom_map: None,
+            color_stops: vec![
+                ColorStop::new(0, [0, 0, 0]),
+                ColorStop::new(255, [255, 255, 255]),
+            ],
+            luma_model: LumaModel::default(),
+            channel_decorrelation: ChannelDecorrelation::default(),
+            dither_space: DitherSpace::default(),
+            edge: EdgeParams::default(),
+            adjustments: Adjustments::default(),
+            crop: None,
+            target_width: 0,
+            target_height: 0,
+            resize_filter: ResizeFilter::default(),
+            gamma_correct_resize: false,
+            pixel_scale: 1,
+        }
+    }
+}
+
+impl DitherSettings {
+    /// Caly potok: skalowanie, korekty, dithering, powiekszenie o `pixel_scale`.
+    pub fn process(&self, img: &DynamicImage) -> DynamicImage {
+        let img = self.prepare(img);
+        let img = adjust(&img, &self.adjustments);
+        self.dither(img)
+    }
+
+    /// Kadrowanie i skalowanie do rozmiaru docelowego podzielonego przez `pixel_scale`.
+    pub fn prepare(&self, img: &DynamicImage) -> DynamicImage {
+        let cropped;
+        let img = match self.crop {
+            Some(rect) => {
+                cropped = crop(img, rect);
+                &cropped
+            }
+            None => img,
+        };
+        let scale = self.pixel_scale.max(1);
+        if self.target_width > 0 && self.target_height > 0 {
+            return resize(
+                img,
+                self.target_width / scale,
+                self.target_height / scale,
+                self.resize_filter,
+                self.gamma_correct_resize,
+            );
+        }
+        img.clone()
+    }
+
+    /// Wynik jako paleta i indeksy; kolory z `palette()` zajmuja pierwsze pozycje.
+    pub fn process_indexed(&self, img: &DynamicImage) -> Result<IndexedImage, ExportError> {
+        IndexedImage::from_image(&self.process(img), &self.palette())
+    }
+
+    /// Kolory, ktore moze zwrocic algorytm, w stalej kolejnosci (czarny pierwszy).
+    /// Pusta, gdy wynik nie ma ustalonej palety: bez ditheringu lub przy ditheringu
+    /// samej luminancji.
+    pub fn palette(&self) -> Vec<[u8; 3]> {
+        const BLACK_WHITE: [[u8; 3]; 2] = [[0, 0, 0], [255, 255, 255]];
+        let duoton = || match self.duoton_colors() {
+            Some((low, high)) => vec![low, high],
+            None => {
+                let mut stops = self.color_stops.clone();
+                stops.sort_by_key(|s| s.position);
+                let mut colors: Vec<[u8; 3]> = Vec::new();
+                for stop in stops {
+                    if !colors.contains(&stop.color) {
+                        colors.push(stop.color);
+                    }
+                }
+                colors
+            }
+        };
+
+        match (self.algorithm, self.mode) {
+            (Algorithm::Original, _) => Vec::new(),
+            (_, DitherMode::Grayscale) => BLACK_WHITE.to_vec(),
+            (Algorithm::Binarize, DitherMode::Colored) => BLACK_WHITE.to_vec(),
+            (Algorithm::Binarize, DitherMode::Duoton) => {
+                let colors = duoton();
+                match (colors.first(), colors.last()) {
+                    (Some(&low), Some(&high)) if low != high => vec![low, high],
+                    (Some(&low), _) => vec![low],
+                    _ => BLACK_WHITE.to_vec(),
+                }
+            }
+            (_, DitherMode::Colored) if self.dither_space.is_luma_only() => Vec::new(),
+            // Kazdy kanal osobno: wierzcholki szescianu RGB, bit 0 = R
+            (_, DitherMode::Colored) => (0..8u8)
+                .map(|i| [i & 1, (i >> 1) & 1, (i >> 2) & 1].map(|b| b * 255))
+                .collect(),
+            (_, DitherMode::Duoton) => duoton(),
+        }
+    }
+
+    /// Sprawdza ustawienia wczytane z pliku, zanim trafia do potoku. Odrzuca wartosci,
+    /// przy ktorych algorytmy panikuja, oraz takie, ktore wymagalyby ogromnych buforow
+    /// lub okien filtrow.
+    pub fn validate(&self) -> Result<(), RecipeError> {
+        let invalid = |e: &str| Err(RecipeError::Parse(e.to_string()));
+        if !(1..=6).contains(&self.bayer_size) {
+            return invalid("bayer_size must be between 1 and 6");
+        }
+        if self.color_stops.is_empty() {
+            return invalid("color_stops must not be empty");
+        }
+        if let Some(map) = &self.custom_map {
+            if map.width == 0 || map.height == 0 || map.values.len() != map.width * map.height {
+                return invalid("custom_map values do not match its width and height");
+            }
+            if map.levels == 0 {
+                return invalid("custom_map levels must be greater than 0");
+            }
+        }
+
+        let d = &self.diffusion;
+        if !(-127..=127).contains(&d.bias) {
+            return invalid("diffusion bias must be between -127 and 127");
+        }
+        if d.noise > 127 {
+            return invalid("diffusion noise must be between 0 and 127");
+        }
+        if !(0.0..=1.0).contains(&d.strength) {
+            return invalid("diffusion strength must be between 0 and 1");
+        }
+        match self.binarization {
+            Binarization::Otsu => {}
+            Binarization::Sauvola { radius, .. }
+            | Binarization::Niblack { radius, .. }
+            | Binarization::Bradley { radius, .. } => {
+                if !(1..=MAX_WINDOW_RADIUS).contains(&radius) {
+                    return invalid("binarization radius must be between 1 and 256");
+                }
+            }
+        }
+        match self.adjustments.denoise {
+            Denoise::None => {}
+            Denoise::Median { radius } | Denoise::Bilateral { radius, .. } => {
+                if !(1..=MAX_DENOISE_RADIUS).contains(&radius) {
+                    return invalid("denoise radius must be between 1 and 16");
+                }
+            }
+        }
+        if !(0.0..=MAX_SHARPEN_RADIUS).contains(&self.adjustments.sharpen.radius) {
+            return invalid("sharpen radius must be between 0 and 32");
+        }
+        if let Equalization::Clahe { tiles, .. } = self.adjustments.equalization
+            && !(1..=MAX_CLAHE_TILES).contains(&tiles)
+        {
+            return invalid("CLAHE tiles must be between 1 and 64");
+        }
+
+        if !(1..=MAX_PIXEL_SCALE).contains(&self.pixel_scale) {
+            return invalid("pixel_scale must be between 1 and 64");
+        }
+        if self.target_width > MAX_DIMENSION || self.target_height > MAX_DIMENSION {
+            return invalid("target size must be at most 16384x16384");
+        }
+        Ok(())
+    }
+
+    /// Rozmiar zrodla po wycieciu, punkt odniesienia dla rozmiaru docelowego.
+    pub fn cropped_size(&self, width: u32, height: u32) -> (u32, u32) {
+        match self.crop.and_then(|rect| rect.clamped(width, height)) {
+            Some(rect) => (rect.width, rect.height),
+            None => (width, height),
+        }
+    }
+
+    /// Etap po korektach: tryb koloru, krawedzie i algorytm. Dithering idzie w mniejszej
+    /// rozdzielczosci, potem wynik jest powiekszany o `pixel_scale`.
+    pub fn dither(&self, mut img: DynamicImage) -> DynamicImage {
+        if self.mode == DitherMode::Grayscale {
+            img = grayscale(&img, self.luma_model);
+        }
+
+        if self.algorithm != Algorithm::Original {
+            img = emphasize_edges(&img, self.edge);
+        }
+
+        let img = if self.mode == DitherMode::Colored && self.algorithm != Algorithm::Original {
+            dither_in_space(img, self.dither_space, |img| self.run_algorithm(img))
+        } else {
+            self.run_algorithm(img)
+        };
+        upscale_nearest(&img, self.pixel_scale.max(1))
+    }
+
+    fn run_algorithm(&self, img: DynamicImage) -> DynamicImage {
+        match self.algorithm {
+            Algorithm::Original => img,
+            Algorithm::Bayer => self.apply_bayer(img),
+            Algorithm::WhiteNoise => self.apply_noise(NoiseKind::WhiteNoise, img),
+            Algorithm::TriangularNoise => self.apply_noise(NoiseKind::Triangular, img),
+            Algorithm::InterleavedGradient => self.apply_noise(NoiseKind::InterleavedGradient, img),
+            Algorithm::CustomMap => self.apply_custom_map(img),
+            Algorithm::Floyd => self.apply_floyd(img),
+            Algorithm::Hybrid => self.apply_hybrid(img),
+            Algorithm::Binarize => self.apply_binarize(img),
+        }
+    }
+
+    fn apply_bayer(&self, img: DynamicImage) -> DynamicImage {
+        let n = 2_usize.pow(self.bayer_size as u32);
+        match self.mode {
+            DitherMode::Grayscale | DitherMode::Colored => {
+                bayer::dither_colored(n, &img, self.decorrelation())
+            }
+            DitherMode::Duoton => match self.duoton_colors() {
+                Some((low, high)) => bayer::dither_duoton(n, &img, low, high, self.luma_model),
+                None => gradient::dither_map(
+                    &ThresholdMap::bayer(n),
+                    &img,
+                    &self.color_stops,
+                    self.luma_model,
+                ),
+            },
+        }
+    }
+
+    fn apply_noise(&self, kind: NoiseKind, img: DynamicImage) -> DynamicImage {
+        match self.mode {
+            DitherMode::Grayscale | DitherMode::Colored => {
+                noise::dither_colored(kind, self.noise_seed, &img, self.decorrelation())
+            }
+            DitherMode::Duoton => match self.duoton_colors() {
+                Some((low, high)) => {
+                    noise::dither_duoton(kind, self.noise_seed, &img, low, high, self.luma_model)
+                }
+                None => gradient::dither_noise(
+                    kind,
+                    self.noise_seed,
+                    &img,
+                    &self.color_stops,
+                    self.luma_model,
+                ),
+            },
+        }
+    }
+
+    fn apply_custom_map(&self, img: DynamicImage) -> DynamicImage {
+        let Some(map) = &self.custom_map else {
+            return img;
+        };
+        match self.mode {
+            DitherMode::Grayscale | DitherMode::Colored => {
+                bayer::dither_colored_map(map, &img, self.decorrelation())
+            }
+            DitherMode::Duoton => match self.duoton_colors() {
+                Some((low, high)) => {
+                    bayer::dither_duoton_map(map, &img, low, high, self.luma_model)
+                }
+                None => gradient::dither_map(map, &img, &self.color_stops, self.luma_model),
+            },
+        }
+    }
+
+    fn apply_floyd(&self, img: DynamicImage) -> DynamicImage {
+        let params = self.diffusion_params();
+        match self.mode {
+            DitherMode::Grayscale | DitherMode::Colored => {
+                floyd_steinberg::dither_colored(&img, params)
+            }
+            DitherMode::Duoton => match self.duoton_colors() {
+                Some((low, high)) => {
+                    floyd_steinberg::dither_duoton(&img, low, high, self.luma_model, params)
+                }
+                None => gradient::dither_floyd(&img, &self.color_stops, self.luma_model, params),
+            },
+        }
+    }
+
+    fn apply_hybrid(&self, img: DynamicImage) -> DynamicImage {
+        let params = self.diffusion_params();
+        let bayer;
+        let map = match &self.custom_map {
+            Some(map) if self.hybrid_custom_map => map,
+            _ => {
+                bayer = ThresholdMap::bayer(2_usize.pow(self.bayer_size as u32));
+                &bayer
+            }
+        };
+
+        match self.mode {
+            DitherMode::Grayscale | DitherMode::Colored => {
+                hybrid::dither_colored(map, &img, params, self.hybrid_blend)
+            }
+            DitherMode::Duoton => match self.duoton_colors() {
+                Some((low, high)) => hybrid::dither_duoton(
+                    map,
+                    &img,
+                    low,
+                    high,
+                    self.luma_model,
+                    params,
+                    self.hybrid_blend,
+                ),
+                None => gradient::dither_hybrid(
+                    map,
+                    &img,
+                    &self.color_stops,
+                    self.luma_model,
+                    params,
+                    self.hybrid_blend,
+                ),
+            },
+        }
+    }
+
+    fn apply_binarize(&self, img: DynamicImage) -> DynamicImage {
+        // Przy gradiencie progowanie ma tylko dwa poziomy: skrajne przystanki wedlug
+        // pozycji, tak samo jak w `palette()`
+        let (low, high) = match self.palette().as_slice() {
+            [low, .., high] => (*low, *high),
+            [only] => (*only, *only),
+            [] => ([0, 0, 0], [255, 255, 255]),
+        };
+        binarize::dither_duoton(self.binarization, &img, low, high, self.luma_model)
+    }
+
+    fn diffusion_params(&self) -> DiffusionParams {
+        DiffusionParams {
+            seed: self.noise_seed,
+            ..self.diffusion
+        }
+    }
+
+    // W trybie Gray rozsuniete kanaly dalyby kolorowy wynik
+    fn decorrelation(&self) -> ChannelDecorrelation {
+        match self.mode {
+            DitherMode::Colored => self.channel_decorrelation,
+            _ => ChannelDecorrelation::None,
+        }
+    }
+
+    // Dwa przystanki na koncach osi to zwykly duoton, reszta idzie przez gradient
+    pub fn duoton_colors(&self) -> Option<([u8; 3], [u8; 3])> {
+        match self.color_stops.as_slice() {
+            [low, high] if low.position == 0 && high.position == 255 => {
+                Some((low.color, high.color))
+            }
+            _ => None,
+        }
+    }
+}
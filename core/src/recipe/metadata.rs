@@ -0,0 +1,344 @@
+use super::{Recipe, RecipeError};
+use crate::export::indexed::{IndexedImage, encode_indexed};
+use image::{DynamicImage, ImageFormat};
+use std::io::Cursor;
+use std::path::Path;
+
+/// Slowo kluczowe chunku iTXt w PNG i prefiks komentarza w JPEG i GIF.
+pub const RECIPE_KEYWORD: &str = "dither-recipe";
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &b in bytes {
+        crc ^= b as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+// Chunki PNG po sygnaturze: (typ, dane, poczatek chunku)
+fn png_chunks(bytes: &[u8]) -> impl Iterator<Item = (&[u8], &[u8], usize)> {
+    let mut pos = 8;
+    std::iter::from_fn(move || {
+        let header = bytes.get(pos..pos + 8)?;
+        let len = u32::from_be_bytes(header[..4].try_into().unwrap()) as usize;
+        let data = bytes.get(pos + 8..pos + 8 + len)?;
+        let start = pos;
+        pos += 12 + len;
+        Some((&header[4..8], data, start))
+    })
+}
+
+fn embed_png(bytes: &[u8], text: &str) -> Result<Vec<u8>, RecipeError> {
+    let iend = png_chunks(bytes)
+        .find(|(kind, _, _)| *kind == b"IEND")
+        .map(|(_, _, start)| start)
+        .ok_or_else(|| RecipeError::Parse("PNG without IEND chunk".to_string()))?;
+
+    // iTXt: slowo kluczowe, bez kompresji, pusty jezyk i tlumaczenie, tekst UTF-8
+    let mut chunk = b"iTXt".to_vec();
+    chunk.extend_from_slice(RECIPE_KEYWORD.as_bytes());
+    chunk.extend_from_slice(&[0, 0, 0, 0, 0]);
+    chunk.extend_from_slice(text.as_bytes());
+
+    let mut out = Vec::with_capacity(bytes.len() + chunk.len() + 8);
+    out.extend_from_slice(&bytes[..iend]);
+    out.extend_from_slice(&((chunk.len() - 4) as u32).to_be_bytes());
+    out.extend_from_slice(&chunk);
+    out.extend_from_slice(&crc32(&chunk).to_be_bytes());
+    out.extend_from_slice(&bytes[iend..]);
+    Ok(out)
+}
+
+fn extract_png(bytes: &[u8]) -> Option<String> {
+    png_chunks(bytes).find_map(|(kind, data, _)| {
+        let rest = data
+            .strip_prefix(RECIPE_KEYWORD.as_bytes())?
+            .strip_prefix(&[0])?;
+        match kind {
+            b"tEXt" => Some(rest.iter().map(|&b| b as char).collect()),
+            b"iTXt" => {
+                // Skompresowany tekst nie jest obslugiwany
+                let [0, _, rest @ ..] = rest else {
+                    return None;
+                };
+                let mut parts = rest.splitn(3, |&b| b == 0);
+                let (_, _, text) = (parts.next()?, parts.next()?, parts.next()?);
+                String::from_utf8(text.to_vec()).ok()
+            }
+            _ => None,
+        }
+    })
+}
+
+// Najwiekszy segment JPEG: dlugosc 16-bitowa liczy tez swoje dwa bajty
+const JPEG_SEGMENT: usize = u16::MAX as usize - 2;
+
+/// Dlugi przepis jest dzielony na kolejne komentarze COM, kazdy z prefiksem.
+fn embed_jpeg(bytes: &[u8], text: &str) -> Result<Vec<u8>, RecipeError> {
+    if !bytes.starts_with(&[0xff, 0xd8]) {
+        return Err(RecipeError::Parse("not a JPEG stream".to_string()));
+    }
+    let prefix = [RECIPE_KEYWORD.as_bytes(), &[0]].concat();
+    let chunk = JPEG_SEGMENT - prefix.len();
+
+    let mut out = Vec::with_capacity(bytes.len() + text.len() + 64);
+    out.extend_from_slice(&bytes[..2]);
+    for part in text.as_bytes().chunks(chunk) {
+        out.extend_from_slice(&[0xff, 0xfe]);
+        out.extend_from_slice(&((prefix.len() + part.len() + 2) as u16).to_be_bytes());
+        out.extend_from_slice(&prefix);
+        out.extend_from_slice(part);
+    }
+    out.extend_from_slice(&bytes[2..]);
+    Ok(out)
+}
+
+fn extract_jpeg(bytes: &[u8]) -> Option<String> {
+    let mut text = Vec::new();
+    let mut pos = 2;
+    // Dane obrazu zaczynaja sie po SOS, dalej nie ma juz komentarzy
+    while let Some(&[0xff, marker]) = bytes.get(pos..pos + 2)
+        && marker != 0xda
+        && marker != 0xd9
+    {
+        let len = u16::from_be_bytes(bytes.get(pos + 2..pos + 4)?.try_into().unwrap()) as usize;
+        let data = bytes.get(pos + 4..pos + 2 + len)?;
+        if marker == 0xfe
+            && let Some(part) = data
+                .strip_prefix(RECIPE_KEYWORD.as_bytes())
+                .and_then(|rest| rest.strip_prefix(&[0]))
+        {
+            text.extend_from_slice(part);
+        }
+        pos += 2 + len;
+    }
+    if text.is_empty() {
+        return None;
+    }
+    String::from_utf8(text).ok()
+}
+
+// Naglowek GIF i logiczny ekran z globalna paleta; dalej zaczynaja sie bloki
+fn gif_blocks_start(bytes: &[u8]) -> Option<usize> {
+    let packed = *bytes.get(10)?;
+    let palette = if packed & 0x80 != 0 {
+        3 << ((packed & 7) + 1)
+    } else {
+        0
+    };
+    let start = 13 + palette;
+    (bytes.starts_with(b"GIF8") && bytes.len() >= start).then_some(start)
+}
+
+// Podbloki danych GIF od `pos`: (dane, pozycja za terminatorem)
+fn gif_sub_blocks(bytes: &[u8], mut pos: usize) -> Option<(Vec<u8>, usize)> {
+    let mut data = Vec::new();
+    loop {
+        let len = *bytes.get(pos)? as usize;
+        pos += 1;
+        if len == 0 {
+            return Some((data, pos));
+        }
+        data.extend_from_slice(bytes.get(pos..pos + len)?);
+        pos += len;
+    }
+}
+
+/// Rozszerzenie komentarza zaraz za globalna paleta.
+fn embed_gif(bytes: &[u8], text: &str) -> Result<Vec<u8>, RecipeError> {
+    let start = gif_blocks_start(bytes)
+        .ok_or_else(|| RecipeError::Parse("not a GIF stream".to_string()))?;
+    let payload = [RECIPE_KEYWORD.as_bytes(), &[0], text.as_bytes()].concat();
+
+    let mut out = Vec::with_capacity(bytes.len() + payload.len() + payload.len() / 255 + 4);
+    out.extend_from_slice(&bytes[..start]);
+    out.extend_from_slice(&[0x21, 0xfe]);
+    for block in payload.chunks(255) {
+        out.push(block.len() as u8);
+        out.extend_from_slice(block);
+    }
+    out.push(0);
+    out.extend_from_slice(&bytes[start..]);
+    Ok(out)
+}
+
+fn extract_gif(bytes: &[u8]) -> Option<String> {
+    let mut pos = gif_blocks_start(bytes)?;
+    loop {
+        match *bytes.get(pos)? {
+            0x21 => {
+                let label = *bytes.get(pos + 1)?;
+                let (data, next) = gif_sub_blocks(bytes, pos + 2)?;
+                if label == 0xfe
+                    && let Some(text) = data
+                        .strip_prefix(RECIPE_KEYWORD.as_bytes())
+                        .and_then(|rest| rest.strip_prefix(&[0]))
+                {
+                    return String::from_utf8(text.to_vec()).ok();
+                }
+                pos = next;
+            }
+            0x2c => {
+                // Deskryptor obrazu, lokalna paleta, rozmiar kodu LZW i dane
+                let packed = *bytes.get(pos + 9)?;
+                let palette = if packed & 0x80 != 0 {
+                    3 << ((packed & 7) + 1)
+                } else {
+                    0
+                };
+                pos = gif_sub_blocks(bytes, pos + 10 + palette + 1)?.1;
+            }
+            _ => return None,
+        }
+    }
+}
+
+/// Dokleja przepis do zakodowanego obrazu: iTXt w PNG, komentarze COM w JPEG i
+/// rozszerzenie komentarza w GIF. Pozostale formaty (WebP, BMP, TIFF i inne) sa
+/// zwracane bez zmian, bez przepisu.
+pub fn embed_recipe(
+    bytes: Vec<u8>,
+    format: ImageFormat,
+    recipe: &Recipe,
+) -> Result<Vec<u8>, RecipeError> {
+    match format {
+        ImageFormat::Png => embed_png(&bytes, &recipe.to_toml()?),
+        ImageFormat::Jpeg => embed_jpeg(&bytes, &recipe.to_toml()?),
+        ImageFormat::Gif => embed_gif(&bytes, &recipe.to_toml()?),
+        _ => Ok(bytes),
+    }
+}
+
+/// Przepis zapisany w metadanych PNG, JPEG lub GIF, jesli plik go zawiera.
+pub fn extract_recipe(bytes: &[u8]) -> Option<Result<Recipe, RecipeError>> {
+    let text = match image::guess_format(bytes).ok()? {
+        ImageFormat::Png => extract_png(bytes)?,
+        ImageFormat::Jpeg => extract_jpeg(bytes)?,
+        ImageFormat::Gif => extract_gif(bytes)?,
+        _ => return None,
+    };
+    Some(Recipe::from_toml(&text))
+}
+
+pub fn read_recipe(path: impl AsRef<Path>) -> Option<Result<Recipe, RecipeError>> {
+    match std::fs::read(path) {
+        Ok(bytes) => extract_recipe(&bytes),
+        Err(e) => Some(Err(e.into())),
+    }
+}
+
+pub fn encode_with_recipe(
+    img: &DynamicImage,
+    format: ImageFormat,
+    recipe: &Recipe,
+) -> Result<Vec<u8>, RecipeError> {
+    // Do 256 kolorow PNG, BMP i GIF sa zapisywane z paleta: mniejsze i z dokladnymi kolorami
+    if matches!(
+        format,
+        ImageFormat::Png | ImageFormat::Bmp | ImageFormat::Gif
+    ) && let Ok(indexed) = IndexedImage::with_limit(img, &recipe.settings.palette(), 256)
+    {
+        return embed_recipe(encode_indexed(&indexed, format)?, format, recipe);
+    }
+
+    // JPEG nie ma kanalu alfa
+    let img = match format {
+        ImageFormat::Jpeg => &DynamicImage::ImageRgb8(img.to_rgb8()),
+        _ => img,
+    };
+    let mut bytes = Cursor::new(Vec::new());
+    img.write_to(&mut bytes, format)?;
+    embed_recipe(bytes.into_inner(), format, recipe)
+}
+
+/// Zapis z formatem wybranym po rozszerzeniu i przepisem w metadanych.
+pub fn save_with_recipe(
+    img: &DynamicImage,
+    path: impl AsRef<Path>,
+    recipe: &Recipe,
+) -> Result<(), RecipeError> {
+    let path = path.as_ref();
+    let format = ImageFormat::from_path(path)?;
+    std::fs::write(path, encode_with_recipe(img, format, recipe)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dither::ordered::threshold_map::ThresholdMap;
+    use crate::pipeline::{Algorithm, DitherSettings};
+    use image::{Rgb, RgbImage};
+
+    fn image() -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(16, 8, |x, y| {
+            Rgb([(x * 16) as u8, (y * 32) as u8, 128])
+        }))
+    }
+
+    fn recipe() -> Recipe {
+        Recipe::new(DitherSettings {
+            algorithm: Algorithm::Floyd,
+            noise_seed: 7,
+            ..DitherSettings::default()
+        })
+    }
+
+    fn round_trip(format: ImageFormat, recipe: &Recipe) {
+        let bytes = encode_with_recipe(&image(), format, recipe).unwrap();
+        assert_eq!(image::guess_format(&bytes).unwrap(), format);
+        assert_eq!(&extract_recipe(&bytes).unwrap().unwrap(), recipe);
+        // Metadane nie psuja samego obrazu
+        let decoded = image::load_from_memory(&bytes).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (16, 8));
+    }
+
+    #[test]
+    fn png_itxt_round_trip() {
+        round_trip(ImageFormat::Png, &recipe());
+    }
+
+    #[test]
+    fn jpeg_comment_round_trip() {
+        round_trip(ImageFormat::Jpeg, &recipe());
+    }
+
+    #[test]
+    fn gif_comment_round_trip() {
+        round_trip(ImageFormat::Gif, &recipe());
+    }
+
+    #[test]
+    fn large_recipe_is_split_across_jpeg_comments() {
+        let mut recipe = recipe();
+        recipe.settings.custom_map = Some(ThresholdMap {
+            values: (0..128 * 128).map(|i| (i * 7919 % 16384) as u16).collect(),
+            width: 128,
+            height: 128,
+            levels: 16384,
+        });
+        assert!(recipe.to_toml().unwrap().len() > u16::MAX as usize);
+        round_trip(ImageFormat::Jpeg, &recipe);
+        round_trip(ImageFormat::Gif, &recipe);
+    }
+
+    #[test]
+    fn png_without_recipe() {
+        let mut bytes = Cursor::new(Vec::new());
+        image().write_to(&mut bytes, ImageFormat::Png).unwrap();
+        assert!(extract_recipe(bytes.get_ref()).is_none());
+    }
+
+    #[test]
+    fn crc_matches_png_reference() {
+        // CRC chunku IEND z kazdego pliku PNG
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+    }
+}
@@ -0,0 +1,317 @@
+pub mod metadata;
+pub mod project;
+
+use crate::export::ExportError;
+use crate::pipeline::DitherSettings;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+
+/// Wersja formatu zapisywana w kazdym przepisie.
+pub const RECIPE_VERSION: u32 = 1;
+
+#[derive(Debug)]
+pub enum RecipeError {
+    Io(std::io::Error),
+    Image(image::ImageError),
+    Export(ExportError),
+    Parse(String),
+    /// Przepis zapisany przez nowsza wersje programu.
+    UnsupportedVersion(u32),
+}
+
+impl fmt::Display for RecipeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "cannot read recipe: {e}"),
+            Self::Image(e) => write!(f, "image error: {e}"),
+            Self::Export(e) => write!(f, "{e}"),
+            Self::Parse(e) => write!(f, "invalid recipe: {e}"),
+            Self::UnsupportedVersion(v) => write!(
+                f,
+                "recipe version {v} is newer than supported version {RECIPE_VERSION}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RecipeError {}
+
+impl From<std::io::Error> for RecipeError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<ExportError> for RecipeError {
+    fn from(e: ExportError) -> Self {
+        Self::Export(e)
+    }
+}
+
+impl From<image::ImageError> for RecipeError {
+    fn from(e: image::ImageError) -> Self {
+        Self::Image(e)
+    }
+}
+
+/// Zapisany komplet ustawien potoku; ten sam plik steruje GUI i przetwarzaniem wsadowym.
+/// Brakujace pola przyjmuja wartosci domyslne.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Recipe {
+    pub version: u32,
+    pub settings: DitherSettings,
+}
+
+impl Recipe {
+    pub fn new(settings: DitherSettings) -> Self {
+        Self {
+            version: RECIPE_VERSION,
+            settings,
+        }
+    }
+
+    // Wersja i poprawnosc ustawien, wspolne dla obu formatow
+    fn check(self) -> Result<Self, RecipeError> {
+        if self.version > RECIPE_VERSION {
+            return Err(RecipeError::UnsupportedVersion(self.version));
+        }
+        self.settings.validate()?;
+        Ok(Self {
+            version: RECIPE_VERSION,
+            ..self
+        })
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self, RecipeError> {
+        toml::from_str::<Self>(text)
+            .map_err(|e| RecipeError::Parse(e.to_string()))?
+            .check()
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, RecipeError> {
+        serde_json::from_str::<Self>(text)
+            .map_err(|e| RecipeError::Parse(e.to_string()))?
+            .check()
+    }
+
+    pub fn to_toml(&self) -> Result<String, RecipeError> {
+        toml::to_string(self).map_err(|e| RecipeError::Parse(e.to_string()))
+    }
+
+    pub fn to_json(&self) -> Result<String, RecipeError> {
+        serde_json::to_string_pretty(self).map_err(|e| RecipeError::Parse(e.to_string()))
+    }
+
+    fn is_json(path: &Path) -> bool {
+        path.extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+    }
+
+    /// Format wybierany po rozszerzeniu: `.json` to JSON, wszystko inne TOML.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, RecipeError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        if Self::is_json(path) {
+            Self::from_json(&text)
+        } else {
+            Self::from_toml(&text)
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), RecipeError> {
+        let path = path.as_ref();
+        let text = if Self::is_json(path) {
+            self.to_json()?
+        } else {
+            self.to_toml()?
+        };
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dither::gradient::ColorStop;
+    use crate::dither::ordered::threshold_map::ThresholdMap;
+    use crate::pipeline::{Algorithm, DitherMode};
+
+    fn sample() -> Recipe {
+        Recipe::new(DitherSettings {
+            algorithm: Algorithm::Hybrid,
+            mode: DitherMode::Duoton,
+            bayer_size: 3,
+            noise_seed: 42,
+            color_stops: vec![
+                ColorStop::new(0, [0, 0, 128]),
+                ColorStop::new(128, [200, 30, 30]),
+                ColorStop::new(255, [255, 255, 0]),
+            ],
+            custom_map: Some(ThresholdMap::bayer(4)),
+            target_width: 320,
+            target_height: 240,
+            ..DitherSettings::default()
+        })
+    }
+
+    #[test]
+    fn toml_round_trip() {
+        let recipe = sample();
+        assert_eq!(
+            Recipe::from_toml(&recipe.to_toml().unwrap()).unwrap(),
+            recipe
+        );
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let recipe = sample();
+        assert_eq!(
+            Recipe::from_json(&recipe.to_json().unwrap()).unwrap(),
+            recipe
+        );
+    }
+
+    #[test]
+    fn missing_fields_use_defaults() {
+        let recipe = Recipe::from_toml("version = 1\n[settings]\nalgorithm = \"floyd\"\n").unwrap();
+        assert_eq!(recipe.settings.algorithm, Algorithm::Floyd);
+        assert_eq!(
+            recipe.settings.bayer_size,
+            DitherSettings::default().bayer_size
+        );
+    }
+
+    #[test]
+    fn rejects_newer_version() {
+        assert!(matches!(
+            Recipe::from_toml("version = 99\n[settings]\n"),
+            Err(RecipeError::UnsupportedVersion(99))
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_settings() {
+        let invalid = [
+            "bayer_size = 0",
+            "bayer_size = 7",
+            "bayer_size = 64",
+            "color_stops = []",
+            "custom_map = { values = [], width = 0, height = 0, levels = 4 }",
+            "custom_map = { values = [0, 1, 2], width = 2, height = 2, levels = 4 }",
+            "custom_map = { values = [0, 1, 2, 3], width = 2, height = 2, levels = 0 }",
+        ];
+        for field in invalid {
+            let text = format!("version = 1\n[settings]\n{field}\n");
+            assert!(
+                matches!(Recipe::from_toml(&text), Err(RecipeError::Parse(_))),
+                "{field}"
+            );
+        }
+
+        let mut recipe = sample();
+        recipe.settings.color_stops.clear();
+        assert!(matches!(
+            Recipe::from_json(&recipe.to_json().unwrap()),
+            Err(RecipeError::Parse(_))
+        ));
+    }
+
+    fn rejects(field: &str) -> bool {
+        let text = format!("version = 1\n[settings]\n{field}\n");
+        matches!(Recipe::from_toml(&text), Err(RecipeError::Parse(_)))
+    }
+
+    #[test]
+    fn rejects_diffusion_bias() {
+        assert!(rejects("diffusion = { bias = 200 }"));
+        assert!(rejects("diffusion = { bias = -40000 }"));
+        assert!(!rejects("diffusion = { bias = -127 }"));
+    }
+
+    #[test]
+    fn rejects_diffusion_noise() {
+        assert!(rejects("diffusion = { noise = 200 }"));
+        assert!(!rejects("diffusion = { noise = 127 }"));
+    }
+
+    #[test]
+    fn rejects_diffusion_strength() {
+        assert!(rejects("diffusion = { strength = 2.0 }"));
+        assert!(rejects("diffusion = { strength = -0.5 }"));
+        assert!(rejects("diffusion = { strength = nan }"));
+        assert!(!rejects("diffusion = { strength = 0.0 }"));
+    }
+
+    #[test]
+    fn rejects_binarization_radius() {
+        assert!(rejects(
+            "binarization = { sauvola = { radius = 0, k = 0.3 } }"
+        ));
+        assert!(rejects(
+            "binarization = { niblack = { radius = 10000, k = -0.2 } }"
+        ));
+        assert!(!rejects(
+            "binarization = { sauvola = { radius = 64, k = 0.3 } }"
+        ));
+    }
+
+    #[test]
+    fn rejects_denoise_radius() {
+        assert!(rejects(
+            "adjustments = { denoise = { median = { radius = 0 } } }"
+        ));
+        assert!(rejects(
+            "adjustments = { denoise = { median = { radius = 1000 } } }"
+        ));
+        assert!(!rejects(
+            "adjustments = { denoise = { median = { radius = 5 } } }"
+        ));
+    }
+
+    #[test]
+    fn rejects_sharpen_radius() {
+        assert!(rejects("adjustments = { sharpen = { radius = 1000.0 } }"));
+        assert!(!rejects("adjustments = { sharpen = { radius = 8.0 } }"));
+    }
+
+    #[test]
+    fn rejects_clahe_tiles() {
+        assert!(rejects(
+            "adjustments = { equalization = { clahe = { tiles = 0, clip_limit = 2.0 } } }"
+        ));
+        assert!(rejects(
+            "adjustments = { equalization = { clahe = { tiles = 1000, clip_limit = 2.0 } } }"
+        ));
+    }
+
+    #[test]
+    fn rejects_pixel_scale() {
+        assert!(rejects("pixel_scale = 0"));
+        assert!(rejects("pixel_scale = 1000"));
+        assert!(!rejects("pixel_scale = 16"));
+    }
+
+    #[test]
+    fn rejects_target_size() {
+        assert!(rejects("target_width = 100000"));
+        assert!(rejects("target_height = 100000"));
+        assert!(!rejects("target_width = 4096\ntarget_height = 4096"));
+    }
+
+    #[test]
+    fn crop_is_not_stored() {
+        let mut recipe = sample();
+        recipe.settings.crop = Some(crate::resize::Crop {
+            x: 1,
+            y: 2,
+            width: 3,
+            height: 4,
+        });
+        let text = recipe.to_toml().unwrap();
+        assert!(!text.contains("crop"));
+        assert_eq!(Recipe::from_toml(&text).unwrap().settings.crop, None);
+    }
+}
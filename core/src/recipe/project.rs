@@ -0,0 +1,277 @@
+use super::{RECIPE_VERSION, Recipe, RecipeError};
+use crate::pipeline::DitherSettings;
+use crate::resize::Crop;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use image::{DynamicImage, ImageFormat};
+use serde::{Deserialize, Serialize};
+use std::io::Cursor;
+use std::path::{Component, Path, PathBuf};
+
+/// Skad projekt bierze obraz zrodlowy.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ImageSource {
+    /// Plik obok projektu. `path` jest zapisywana wzgledem pliku projektu, zeby
+    /// przeniesiony katalog dalej dzialal; `absolute` to zapas, gdy przeniesiono sam projekt.
+    File {
+        path: PathBuf,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        absolute: Option<PathBuf>,
+    },
+    /// Kopia obrazu w PNG zakodowana base64.
+    Embedded { png: String },
+}
+
+/// Stan widoku w GUI; nie wplywa na wynik.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ViewState {
+    pub zoom: f32,
+    /// Przesuniecie obszaru przewijania w punktach ekranu.
+    pub scroll: [f32; 2],
+    pub lock_aspect_ratio: bool,
+}
+
+impl Default for ViewState {
+    fn default() -> Self {
+        Self {
+            zoom: 1.0,
+            scroll: [0.0; 2],
+            lock_aspect_ratio: true,
+        }
+    }
+}
+
+/// Praca w toku: zrodlo, komplet ustawien, wycinek oraz widok.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Project {
+    pub version: u32,
+    pub source: ImageSource,
+    pub settings: DitherSettings,
+    /// Wycinek zrodla; w `settings` nie jest zapisywany.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crop: Option<Crop>,
+    #[serde(default)]
+    pub view: ViewState,
+}
+
+// Sciezka `path` zapisana wzgledem katalogu `base`; None, gdy leza na roznych dyskach
+fn relative_path(path: &Path, base: &Path) -> Option<PathBuf> {
+    let resolve = |p: &Path| std::fs::canonicalize(p).or_else(|_| std::path::absolute(p));
+    let path = resolve(path).ok()?;
+    let base = resolve(base).ok()?;
+
+    let mut path_parts = path.components().peekable();
+    let mut base_parts = base.components().peekable();
+    if path_parts.peek() != base_parts.peek() {
+        return None;
+    }
+    while let (Some(a), Some(b)) = (path_parts.peek(), base_parts.peek())
+        && a == b
+    {
+        path_parts.next();
+        base_parts.next();
+    }
+
+    let mut relative: PathBuf = base_parts.map(|_| Component::ParentDir).collect();
+    relative.extend(path_parts);
+    Some(relative)
+}
+
+fn project_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    }
+}
+
+impl Project {
+    /// Projekt odwolujacy sie do pliku obrazu.
+    pub fn linked(image: impl Into<PathBuf>, settings: DitherSettings, view: ViewState) -> Self {
+        Self {
+            version: RECIPE_VERSION,
+            source: ImageSource::File {
+                path: image.into(),
+                absolute: None,
+            },
+            crop: settings.crop,
+            settings,
+            view,
+        }
+    }
+
+    /// Projekt niezalezny od plikow: obraz zapisany w srodku jako PNG.
+    pub fn embedded(
+        img: &DynamicImage,
+        settings: DitherSettings,
+        view: ViewState,
+    ) -> Result<Self, RecipeError> {
+        let mut png = Cursor::new(Vec::new());
+        img.write_to(&mut png, ImageFormat::Png)?;
+        Ok(Self {
+            version: RECIPE_VERSION,
+            source: ImageSource::Embedded {
+                png: BASE64.encode(png.into_inner()),
+            },
+            crop: settings.crop,
+            settings,
+            view,
+        })
+    }
+
+    /// Wczytanie projektu; sciezka zrodla jest zamieniana na bezwzgledna. Najpierw
+    /// sprawdzana jest sciezka wzgledna, potem zapisana bezwzgledna.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, RecipeError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        let mut project: Self = if Recipe::is_json(path) {
+            serde_json::from_str(&text).map_err(|e| RecipeError::Parse(e.to_string()))?
+        } else {
+            toml::from_str(&text).map_err(|e| RecipeError::Parse(e.to_string()))?
+        };
+        if project.version > RECIPE_VERSION {
+            return Err(RecipeError::UnsupportedVersion(project.version));
+        }
+        project.settings.validate()?;
+        project.settings.crop = project.crop;
+        project.version = RECIPE_VERSION;
+
+        if let ImageSource::File {
+            path: image,
+            absolute,
+        } = &mut project.source
+        {
+            let relative = project_dir(path).join(&*image);
+            *image = match absolute.take() {
+                Some(fallback) if !relative.exists() && fallback.exists() => fallback,
+                _ => relative,
+            };
+        }
+        Ok(project)
+    }
+
+    /// Zapis w TOML lub JSON (po rozszerzeniu, jak przepisy).
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), RecipeError> {
+        let path = path.as_ref();
+        let mut project = self.clone();
+        if let ImageSource::File {
+            path: image,
+            absolute,
+        } = &mut project.source
+        {
+            *absolute = std::path::absolute(&*image).ok();
+            if let Some(relative) = relative_path(image, project_dir(path)) {
+                *image = relative;
+            }
+        }
+
+        let text = if Recipe::is_json(path) {
+            serde_json::to_string_pretty(&project).map_err(|e| RecipeError::Parse(e.to_string()))?
+        } else {
+            toml::to_string(&project).map_err(|e| RecipeError::Parse(e.to_string()))?
+        };
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+
+    /// Obraz zrodlowy z pliku albo z kopii w projekcie.
+    pub fn load_image(&self) -> Result<DynamicImage, RecipeError> {
+        match &self.source {
+            ImageSource::File { path, .. } => Ok(image::open(path)?),
+            ImageSource::Embedded { png } => {
+                let bytes = BASE64
+                    .decode(png)
+                    .map_err(|e| RecipeError::Parse(e.to_string()))?;
+                Ok(image::load_from_memory_with_format(
+                    &bytes,
+                    ImageFormat::Png,
+                )?)
+            }
+        }
+    }
+
+    /// Sciezka pliku zrodlowego, jesli obraz nie jest osadzony.
+    pub fn source_path(&self) -> Option<&Path> {
+        match &self.source {
+            ImageSource::File { path, .. } => Some(path),
+            ImageSource::Embedded { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Pusty katalog tymczasowy, osobny dla kazdego testu
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("dither-project-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write_image(path: &Path) {
+        image::RgbImage::new(2, 2).save(path).unwrap();
+    }
+
+    #[test]
+    fn moved_folder_keeps_relative_path() {
+        let dir = temp_dir("relative");
+        let old = dir.join("old");
+        std::fs::create_dir(&old).unwrap();
+        write_image(&old.join("source.png"));
+
+        let settings = DitherSettings {
+            crop: Some(Crop {
+                x: 0,
+                y: 1,
+                width: 2,
+                height: 1,
+            }),
+            ..Default::default()
+        };
+        let project = Project::linked(old.join("source.png"), settings, ViewState::default());
+        project.save(old.join("project.toml")).unwrap();
+        let text = std::fs::read_to_string(old.join("project.toml")).unwrap();
+        assert!(text.contains("path = \"source.png\""), "{text}");
+
+        let new = dir.join("new");
+        std::fs::rename(&old, &new).unwrap();
+        let loaded = Project::load(new.join("project.toml")).unwrap();
+        assert_eq!(loaded.source_path(), Some(new.join("source.png").as_path()));
+        assert_eq!(loaded.settings.crop, project.crop);
+        assert!(loaded.load_image().is_ok());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn moved_project_falls_back_to_absolute_path() {
+        let dir = temp_dir("absolute");
+        write_image(&dir.join("source.png"));
+        std::fs::create_dir_all(dir.join("old")).unwrap();
+        std::fs::create_dir_all(dir.join("new/nested")).unwrap();
+
+        let project = Project::linked(
+            dir.join("source.png"),
+            DitherSettings::default(),
+            ViewState::default(),
+        );
+        project.save(dir.join("old/project.json")).unwrap();
+
+        // Sciezka wzgledna `../source.png` z nowego miejsca nie istnieje
+        std::fs::rename(
+            dir.join("old/project.json"),
+            dir.join("new/nested/project.json"),
+        )
+        .unwrap();
+        let loaded = Project::load(dir.join("new/nested/project.json")).unwrap();
+        assert_eq!(loaded.source_path(), Some(dir.join("source.png").as_path()));
+        assert!(loaded.load_image().is_ok());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,221 @@
+use crate::luma::{linear_to_srgb, srgb_to_linear};
+use image::{DynamicImage, Rgba32FImage, imageops};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResizeFilter {
+    #[default]
+    Nearest,
+    Triangle,
+    CatmullRom,
+    Gaussian,
+    Lanczos3,
+    /// Srednia z pokrytego obszaru, najlepsza przy mocnym pomniejszaniu.
+    Area,
+}
+
+impl ResizeFilter {
+    fn filter_type(self) -> Option<imageops::FilterType> {
+        match self {
+            Self::Nearest => Some(imageops::FilterType::Nearest),
+            Self::Triangle => Some(imageops::FilterType::Triangle),
+            Self::CatmullRom => Some(imageops::FilterType::CatmullRom),
+            Self::Gaussian => Some(imageops::FilterType::Gaussian),
+            Self::Lanczos3 => Some(imageops::FilterType::Lanczos3),
+            Self::Area => None,
+        }
+    }
+}
+
+// Wagi pokrycia piksela wyjsciowego przez piksele wejsciowe w jednym wymiarze
+fn area_weights(src: u32, dst: u32) -> Vec<Vec<(usize, f32)>> {
+    let scale = src as f64 / dst as f64;
+    (0..dst)
+        .map(|o| {
+            let start = o as f64 * scale;
+            let end = start + scale;
+            let mut weights = Vec::new();
+            let mut i = start.floor() as usize;
+            while (i as f64) < end && i < src as usize {
+                let cover = (end.min(i as f64 + 1.0) - start.max(i as f64)) / scale;
+                if cover > 0.0 {
+                    weights.push((i, cover as f32));
+                }
+                i += 1;
+            }
+            weights
+        })
+        .collect()
+}
+
+fn resize_area(img: &Rgba32FImage, width: u32, height: u32) -> Rgba32FImage {
+    let (src_w, src_h) = img.dimensions();
+    let src = img.as_raw();
+
+    let cols = area_weights(src_w, width);
+    let mut horizontal = vec![0.0f32; (width * src_h * 4) as usize];
+    horizontal
+        .par_chunks_exact_mut((width * 4) as usize)
+        .enumerate()
+        .for_each(|(y, row)| {
+            for (x, weights) in cols.iter().enumerate() {
+                for &(sx, wgt) in weights {
+                    let i = (y * src_w as usize + sx) * 4;
+                    for c in 0..4 {
+                        row[x * 4 + c] += src[i + c] * wgt;
+                    }
+                }
+            }
+        });
+
+    let rows = area_weights(src_h, height);
+    let mut out = vec![0.0f32; (width * height * 4) as usize];
+    out.par_chunks_exact_mut((width * 4) as usize)
+        .zip(rows.par_iter())
+        .for_each(|(row, weights)| {
+            for &(sy, wgt) in weights {
+                let src_row = &horizontal[sy * (width * 4) as usize..][..(width * 4) as usize];
+                for (o, s) in row.iter_mut().zip(src_row) {
+                    *o += s * wgt;
+                }
+            }
+        });
+
+    Rgba32FImage::from_raw(width, height, out).unwrap()
+}
+
+fn map_rgb(img: &mut Rgba32FImage, f: fn(f32) -> f32) {
+    img.par_chunks_exact_mut(4).for_each(|pixel| {
+        for v in &mut pixel[..3] {
+            *v = f(v.clamp(0.0, 1.0));
+        }
+    });
+}
+
+/// Skalowanie do dokladnego rozmiaru; `gamma_correct` filtruje w swietle liniowym,
+/// co nie przyciemnia drobnych jasnych detali przy pomniejszaniu.
+pub fn resize(
+    img: &DynamicImage,
+    width: u32,
+    height: u32,
+    filter: ResizeFilter,
+    gamma_correct: bool,
+) -> DynamicImage {
+    let width = width.max(1);
+    let height = height.max(1);
+    if img.width() == width && img.height() == height {
+        return img.clone();
+    }
+
+    if !gamma_correct && let Some(filter_type) = filter.filter_type() {
+        return img.resize_exact(width, height, filter_type);
+    }
+
+    let mut buffer = img.to_rgba32f();
+    if gamma_correct {
+        map_rgb(&mut buffer, srgb_to_linear);
+    }
+
+    let mut resized = match filter.filter_type() {
+        Some(filter_type) => imageops::resize(&buffer, width, height, filter_type),
+        None => resize_area(&buffer, width, height),
+    };
+
+    if gamma_correct {
+        map_rgb(&mut resized, linear_to_srgb);
+    }
+    DynamicImage::ImageRgba8(DynamicImage::ImageRgba32F(resized).to_rgba8())
+}
+
+/// Prostokat wycinany ze zrodla przed skalowaniem.
+#[derive(Debug, PartialEq, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Crop {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Crop {
+    /// Czesc prostokata lezaca w obrazie; `None`, gdy nic z niego nie zostaje.
+    pub fn clamped(self, width: u32, height: u32) -> Option<Self> {
+        let x = self.x.min(width);
+        let y = self.y.min(height);
+        let w = self.width.min(width - x);
+        let h = self.height.min(height - y);
+        (w > 0 && h > 0).then_some(Self {
+            x,
+            y,
+            width: w,
+            height: h,
+        })
+    }
+}
+
+/// Wycinek obrazu; prostokat poza obrazem zostawia go bez zmian.
+pub fn crop(img: &DynamicImage, rect: Crop) -> DynamicImage {
+    match rect.clamped(img.width(), img.height()) {
+        Some(r) => img.crop_imm(r.x, r.y, r.width, r.height),
+        None => img.clone(),
+    }
+}
+
+/// Powiekszenie o calkowity wspolczynnik metoda najblizszego sasiada (efekt "grubych pikseli").
+pub fn upscale_nearest(img: &DynamicImage, factor: u32) -> DynamicImage {
+    if factor <= 1 {
+        return img.clone();
+    }
+    img.resize_exact(
+        img.width() * factor,
+        img.height() * factor,
+        imageops::FilterType::Nearest,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: u32, y: u32, width: u32, height: u32) -> Crop {
+        Crop {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn clamped_inside_is_unchanged() {
+        assert_eq!(rect(2, 3, 10, 5).clamped(20, 10), Some(rect(2, 3, 10, 5)));
+        assert_eq!(rect(0, 0, 20, 10).clamped(20, 10), Some(rect(0, 0, 20, 10)));
+    }
+
+    #[test]
+    fn clamped_cuts_overhang() {
+        assert_eq!(rect(15, 8, 10, 10).clamped(20, 10), Some(rect(15, 8, 5, 2)));
+        assert_eq!(
+            rect(0, 0, 500, 400).clamped(20, 10),
+            Some(rect(0, 0, 20, 10))
+        );
+    }
+
+    #[test]
+    fn clamped_outside_is_none() {
+        assert_eq!(rect(20, 0, 5, 5).clamped(20, 10), None);
+        assert_eq!(rect(0, 50, 5, 5).clamped(20, 10), None);
+        assert_eq!(rect(0, 0, 0, 5).clamped(20, 10), None);
+        assert_eq!(rect(0, 0, 5, 5).clamped(0, 0), None);
+    }
+
+    #[test]
+    fn crop_outside_keeps_image() {
+        let img = DynamicImage::new_rgb8(20, 10);
+        let cropped = crop(&img, rect(5, 5, 100, 100));
+        assert_eq!((cropped.width(), cropped.height()), (15, 5));
+        let same = crop(&img, rect(30, 30, 5, 5));
+        assert_eq!((same.width(), same.height()), (20, 10));
+    }
+}
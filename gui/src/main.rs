@@ -1,10 +1,10 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use dither_core;
-
+use dither_core::{Algorithm, DitherMode};
 use eframe::egui;
-use image::{DynamicImage, imageops};
+use image::DynamicImage;
 use rfd::FileDialog;
+use std::path::PathBuf;
 
 fn main() -> eframe::Result<()> {
     let options = eframe::NativeOptions {
@@ -21,36 +21,30 @@ fn main() -> eframe::Result<()> {
     )
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
-enum DitherAlgorythm {
-    Original,
-    Bayer,
-    Floyd,
-}
-
-#[derive(Debug, PartialEq, Clone, Copy)]
-enum DitherMode {
-    Grayscale,
-    Colored,
-    Duoton,
-}
-
 struct MyApp {
     original_image: Option<DynamicImage>,
+    source_path: Option<PathBuf>,
     raw_image: Option<DynamicImage>,
     texture: Option<egui::TextureHandle>,
 
-    selected_algorythm: DitherAlgorythm,
-    selected_mode: DitherMode,
-    dither_bayer_size: usize,
+    settings: dither_core::DitherSettings,
+    custom_map_name: String,
+    preset_status: String,
+    embedded_recipe: Option<dither_core::Recipe>,
+    embed_source: bool,
+
+    show_device_export: bool,
+    device_format: dither_core::DeviceFormat,
+    device_options: dither_core::PackOptions,
+    device_name: String,
+    device_status: String,
 
-    color_low: [u8; 3],
-    color_high: [u8; 3],
-    contrast: f32,
+    histogram_before: Option<[u32; 256]>,
+    histogram_after: Option<[u32; 256]>,
 
     zoom_factor: f32,
-    target_width: u32,
-    target_height: u32,
+    scroll_offset: egui::Vec2,
+    restore_scroll: Option<egui::Vec2>,
     lock_aspect_ratio: bool,
 }
 
@@ -58,17 +52,24 @@ impl Default for MyApp {
     fn default() -> Self {
         Self {
             original_image: None,
+            source_path: None,
             raw_image: None,
             texture: None,
-            selected_algorythm: DitherAlgorythm::Original,
-            selected_mode: DitherMode::Grayscale,
-            dither_bayer_size: 2,
-            color_low: [0, 0, 0],
-            color_high: [255, 255, 255],
-            contrast: 0.0,
+            settings: dither_core::DitherSettings::default(),
+            custom_map_name: String::new(),
+            preset_status: String::new(),
+            embedded_recipe: None,
+            embed_source: false,
+            show_device_export: false,
+            device_format: dither_core::DeviceFormat::default(),
+            device_options: dither_core::PackOptions::default(),
+            device_name: "image".to_string(),
+            device_status: String::new(),
+            histogram_before: None,
+            histogram_after: None,
             zoom_factor: 1.0,
-            target_width: 0,
-            target_height: 0,
+            scroll_offset: egui::Vec2::ZERO,
+            restore_scroll: None,
             lock_aspect_ratio: true,
         }
     }
@@ -76,82 +77,262 @@ impl Default for MyApp {
 
 impl MyApp {
     fn apply_effect(&mut self) {
-        let Some(mut img) = self.original_image.clone() else {
+        let Some(img) = &self.original_image else {
             return;
         };
 
-        if self.target_width > 0 && self.target_height > 0 {
-            img = img.resize_exact(
-                self.target_width,
-                self.target_height,
-                imageops::FilterType::Nearest,
-            );
+        let img = self.settings.prepare(img);
+        self.histogram_before = Some(dither_core::histogram(&img));
+        let img = dither_core::adjust(&img, &self.settings.adjustments);
+        self.histogram_after = Some(dither_core::histogram(&img));
+
+        self.raw_image = Some(self.settings.dither(img));
+        self.texture = None;
+    }
+
+    fn load_image(&mut self) {
+        if let Some(path) = FileDialog::new()
+            .add_filter("Images", &["jpg", "jpeg", "png", "gif", "webp", "bmp"])
+            .pick_file()
+            && let Ok(img) = image::open(&path)
+        {
+            self.settings.crop = None;
+            self.settings.target_width = img.width();
+            self.settings.target_height = img.height();
+            self.original_image = Some(img);
+            self.device_name = path
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned();
+            self.source_path = Some(path.clone());
+            // Obraz zapisany przez program niesie swoje ustawienia; uzytkownik decyduje,
+            // czy je przywrocic, a bledny przepis nie trafia do potoku
+            self.embedded_recipe = match dither_core::read_recipe(&path) {
+                Some(Ok(recipe)) => Some(recipe),
+                Some(Err(e)) => {
+                    self.preset_status = format!("Embedded settings ignored: {e}");
+                    None
+                }
+                None => None,
+            };
+            self.apply_effect();
         }
+    }
 
-        if self.contrast != 0.0 {
-            img = DynamicImage::ImageRgba8(imageops::contrast(&img, self.contrast));
+    fn load_custom_map(&mut self) -> bool {
+        let Some(path) = FileDialog::new()
+            .add_filter(
+                "Threshold map",
+                &["png", "bmp", "jpg", "webp", "txt", "csv"],
+            )
+            .pick_file()
+        else {
+            return false;
+        };
+
+        match dither_core::ThresholdMap::load(&path) {
+            Ok(map) => {
+                self.custom_map_name = format!(
+                    "{} ({}x{})",
+                    path.file_name().unwrap_or_default().to_string_lossy(),
+                    map.width,
+                    map.height
+                );
+                self.settings.custom_map = Some(map);
+            }
+            Err(e) => {
+                self.settings.custom_map = None;
+                self.custom_map_name = e.to_string();
+            }
         }
+        true
+    }
 
-        if self.selected_mode == DitherMode::Grayscale {
-            img = img.grayscale();
+    fn save_image(&mut self) {
+        if let Some(img) = &self.raw_image
+            && let Some(path) = FileDialog::new().set_file_name("output.png").save_file()
+        {
+            let recipe = dither_core::Recipe::new(self.settings.clone());
+            self.preset_status = match dither_core::save_with_recipe(img, &path, &recipe) {
+                Ok(()) => format!("Saved {}", path.display()),
+                Err(e) => format!("{}: {e}", path.display()),
+            };
         }
+    }
 
-        self.raw_image = Some(self.process_dithering(img));
-        self.texture = None;
+    fn export_for_device(&mut self) {
+        let Some(img) = &self.raw_image else {
+            return;
+        };
+        let format = self.device_format;
+        let Some(path) = FileDialog::new()
+            .add_filter("Device", &[format.extension()])
+            .set_file_name(format!("{}.{}", self.device_name, format.extension()))
+            .save_file()
+        else {
+            return;
+        };
+
+        let result = dither_core::IndexedImage::from_image(img, &self.settings.palette())
+            .and_then(|indexed| format.export(&indexed, &self.device_options, &self.device_name))
+            .and_then(|bytes| Ok(std::fs::write(&path, bytes)?));
+        self.device_status = match result {
+            Ok(()) => format!("Saved {}", path.display()),
+            Err(e) => e.to_string(),
+        };
+    }
+
+    fn restore_embedded_recipe(&mut self) {
+        let Some(recipe) = self.embedded_recipe.take() else {
+            return;
+        };
+        self.settings = dither_core::DitherSettings {
+            crop: self.settings.crop,
+            ..recipe.settings
+        };
+        self.clamp_crop();
+        self.custom_map_name = match &self.settings.custom_map {
+            Some(map) => format!("Embedded map ({}x{})", map.width, map.height),
+            None => String::new(),
+        };
+        self.preset_status = "Restored embedded settings".to_string();
     }
 
-    fn process_dithering(&self, img: DynamicImage) -> DynamicImage {
-        match self.selected_algorythm {
-            DitherAlgorythm::Original => img,
-            DitherAlgorythm::Bayer => self.apply_bayer(img),
-            DitherAlgorythm::Floyd => self.apply_floyd(img),
+    // Wycinek z pliku moze nie pasowac do wczytanego obrazu
+    fn clamp_crop(&mut self) {
+        if let Some(img) = &self.original_image {
+            self.settings.crop = self
+                .settings
+                .crop
+                .and_then(|rect| rect.clamped(img.width(), img.height()));
         }
     }
 
-    fn apply_bayer(&self, img: DynamicImage) -> DynamicImage {
-        let n = 2_usize.pow(self.dither_bayer_size as u32);
-        match self.selected_mode {
-            DitherMode::Grayscale | DitherMode::Colored => {
-                dither_core::bayer_dither_colored(n, &img)
+    fn save_preset(&mut self) {
+        let Some(path) = FileDialog::new()
+            .add_filter("Recipe", &["toml", "json"])
+            .set_file_name("preset.toml")
+            .save_file()
+        else {
+            return;
+        };
+        self.preset_status = match dither_core::Recipe::new(self.settings.clone()).save(&path) {
+            Ok(()) => format!("Saved {}", path.display()),
+            Err(e) => e.to_string(),
+        };
+    }
+
+    fn load_preset(&mut self) -> bool {
+        let Some(path) = FileDialog::new()
+            .add_filter("Recipe", &["toml", "json"])
+            .pick_file()
+        else {
+            return false;
+        };
+
+        match dither_core::Recipe::load(&path) {
+            Ok(recipe) => {
+                // Wycinek nalezy do obrazu, nie do przepisu
+                self.settings = dither_core::DitherSettings {
+                    crop: self.settings.crop,
+                    ..recipe.settings
+                };
+                self.clamp_crop();
+                // Przepis bez rozmiaru zostawia rozmiar wczytanego obrazu
+                if let Some(img) = &self.original_image
+                    && (self.settings.target_width == 0 || self.settings.target_height == 0)
+                {
+                    self.settings.target_width = img.width();
+                    self.settings.target_height = img.height();
+                }
+                self.custom_map_name = match &self.settings.custom_map {
+                    Some(map) => format!("Preset map ({}x{})", map.width, map.height),
+                    None => String::new(),
+                };
+                self.preset_status = format!(
+                    "Loaded {}",
+                    path.file_name().unwrap_or_default().to_string_lossy()
+                );
+                true
             }
-            DitherMode::Duoton => {
-                dither_core::bayer_dither_duoton(n, &img, self.color_low, self.color_high)
+            Err(e) => {
+                self.preset_status = e.to_string();
+                false
             }
         }
     }
 
-    fn apply_floyd(&self, img: DynamicImage) -> DynamicImage {
-        match self.selected_mode {
-            DitherMode::Grayscale | DitherMode::Colored => dither_core::floyd_dither_colored(&img),
-            DitherMode::Duoton => {
-                dither_core::floyd_dither_duoton(&img, self.color_low, self.color_high)
+    fn save_project(&mut self) {
+        let Some(img) = &self.original_image else {
+            return;
+        };
+        let Some(path) = FileDialog::new()
+            .add_filter("Project", &["dproj", "json"])
+            .set_file_name("project.dproj")
+            .save_file()
+        else {
+            return;
+        };
+
+        let view = dither_core::ViewState {
+            zoom: self.zoom_factor,
+            scroll: self.scroll_offset.into(),
+            lock_aspect_ratio: self.lock_aspect_ratio,
+        };
+        let settings = self.settings.clone();
+        // Bez pliku zrodlowego (np. po zmianie na dysku) obraz trafia do projektu
+        let project = match &self.source_path {
+            Some(source) if !self.embed_source && source.exists() => {
+                Ok(dither_core::Project::linked(source, settings, view))
             }
-        }
+            _ => dither_core::Project::embedded(img, settings, view),
+        };
+        self.preset_status = match project.and_then(|p| p.save(&path)) {
+            Ok(()) => format!("Saved {}", path.display()),
+            Err(e) => e.to_string(),
+        };
     }
 
-    fn load_image(&mut self) {
-        if let Some(path) = FileDialog::new()
-            .add_filter("Images", &["jpg", "png", "webp", "bmp"])
+    fn open_project(&mut self) -> bool {
+        let Some(path) = FileDialog::new()
+            .add_filter("Project", &["dproj", "json"])
             .pick_file()
-        {
-            if let Ok(img) = image::open(&path) {
-                self.target_width = img.width();
-                self.target_height = img.height();
+        else {
+            return false;
+        };
+
+        let project = dither_core::Project::load(&path);
+        match project.and_then(|p| p.load_image().map(|img| (p, img))) {
+            Ok((project, img)) => {
+                self.source_path = project.source_path().map(PathBuf::from);
+                self.embed_source = self.source_path.is_none();
                 self.original_image = Some(img);
-                self.apply_effect();
+                self.settings = project.settings;
+                self.clamp_crop();
+                self.zoom_factor = project.view.zoom.clamp(0.1, 10.0);
+                self.restore_scroll = Some(project.view.scroll.into());
+                self.lock_aspect_ratio = project.view.lock_aspect_ratio;
+                self.embedded_recipe = None;
+                self.custom_map_name = match &self.settings.custom_map {
+                    Some(map) => format!("Project map ({}x{})", map.width, map.height),
+                    None => String::new(),
+                };
+                self.preset_status = format!(
+                    "Opened {}",
+                    path.file_name().unwrap_or_default().to_string_lossy()
+                );
+                true
             }
-        }
-    }
-
-    fn save_image(&self) {
-        if let Some(img) = &self.raw_image {
-            if let Some(path) = FileDialog::new().set_file_name("output.png").save_file() {
-                let _ = img.save(path);
+            Err(e) => {
+                self.preset_status = e.to_string();
+                false
             }
         }
     }
 
-    fn ui_file_section(&mut self, ui: &mut egui::Ui) {
+    fn ui_file_section(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
         ui.group(|ui| {
             ui.horizontal(|ui| {
                 if ui.button("📂 Load").clicked() {
@@ -161,7 +342,47 @@ impl MyApp {
                     self.save_image();
                 }
             });
+            ui.horizontal(|ui| {
+                if ui.button("📂 Load Preset").clicked() {
+                    changed |= self.load_preset();
+                }
+                if ui.button("💾 Save Preset").clicked() {
+                    self.save_preset();
+                }
+            });
+            ui.add_enabled_ui(self.raw_image.is_some(), |ui| {
+                if ui.button("📟 Export for device").clicked() {
+                    self.show_device_export = true;
+                }
+            });
+            ui.horizontal(|ui| {
+                if ui.button("📂 Open Project").clicked() {
+                    changed |= self.open_project();
+                }
+                ui.add_enabled_ui(self.original_image.is_some(), |ui| {
+                    if ui.button("💾 Save Project").clicked() {
+                        self.save_project();
+                    }
+                });
+                ui.checkbox(&mut self.embed_source, "Embed image");
+            });
+            if !self.preset_status.is_empty() {
+                ui.label(&self.preset_status);
+            }
+            if self.embedded_recipe.is_some() {
+                ui.label("This image contains dithering settings.");
+                ui.horizontal(|ui| {
+                    if ui.button("Restore settings").clicked() {
+                        self.restore_embedded_recipe();
+                        changed = true;
+                    }
+                    if ui.button("Ignore").clicked() {
+                        self.embedded_recipe = None;
+                    }
+                });
+            }
         });
+        changed
     }
 
     fn ui_resize_section(&mut self, ui: &mut egui::Ui) -> bool {
@@ -169,21 +390,149 @@ impl MyApp {
         ui.group(|ui| {
             ui.label("Resize");
             ui.horizontal(|ui| {
-                let w = ui.add(egui::DragValue::new(&mut self.target_width).prefix("W: "));
+                let w = ui.add(egui::DragValue::new(&mut self.settings.target_width).prefix("W: "));
                 ui.checkbox(&mut self.lock_aspect_ratio, "Lock");
-                let h = ui.add(egui::DragValue::new(&mut self.target_height).prefix("H: "));
+                let h =
+                    ui.add(egui::DragValue::new(&mut self.settings.target_height).prefix("H: "));
 
                 if w.changed() || h.changed() {
                     self.handle_aspect_ratio(w.changed(), h.changed());
                     changed = true;
                 }
             });
-            if ui.button("Reset Size").clicked() {
-                if let Some(img) = &self.original_image {
-                    self.target_width = img.width();
-                    self.target_height = img.height();
+            if ui.button("Reset Size").clicked()
+                && let Some(img) = &self.original_image
+            {
+                let (width, height) = self.settings.cropped_size(img.width(), img.height());
+                self.settings.target_width = width;
+                self.settings.target_height = height;
+                changed = true;
+            }
+
+            if self.ui_crop(ui) {
+                // Nowy wycinek zmienia proporcje; szerokosc zostaje, wysokosc sie dopasowuje
+                self.handle_aspect_ratio(true, false);
+                changed = true;
+            }
+
+            egui::ComboBox::from_label("Filter")
+                .selected_text(format!("{:?}", self.settings.resize_filter))
+                .show_ui(ui, |ui| {
+                    use dither_core::ResizeFilter;
+                    for filter in [
+                        ResizeFilter::Nearest,
+                        ResizeFilter::Triangle,
+                        ResizeFilter::CatmullRom,
+                        ResizeFilter::Gaussian,
+                        ResizeFilter::Lanczos3,
+                        ResizeFilter::Area,
+                    ] {
+                        changed |= ui
+                            .selectable_value(
+                                &mut self.settings.resize_filter,
+                                filter,
+                                format!("{filter:?}"),
+                            )
+                            .changed();
+                    }
+                });
+            changed |= ui
+                .checkbox(&mut self.settings.gamma_correct_resize, "Gamma-correct")
+                .changed();
+            changed |= ui
+                .add(egui::Slider::new(&mut self.settings.pixel_scale, 1..=16).text("Pixel scale"))
+                .changed();
+        });
+        changed
+    }
+
+    fn ui_adjustments_section(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+        let adj = &mut self.settings.adjustments;
+        ui.group(|ui| {
+            ui.label("Adjustments");
+            if let (Some(before), Some(after)) = (&self.histogram_before, &self.histogram_after) {
+                ui_histogram(ui, before, after);
+            }
+
+            changed |= ui_equalization(ui, &mut adj.equalization);
+            changed |= ui
+                .add(egui::Slider::new(&mut adj.brightness, -128.0..=128.0).text("Brightness"))
+                .changed();
+            changed |= ui
+                .add(egui::Slider::new(&mut adj.contrast, -50.0..=50.0).text("Contrast"))
+                .changed();
+            changed |= ui_denoise(ui, &mut adj.denoise);
+            changed |= ui
+                .add(egui::Slider::new(&mut adj.sharpen.amount, 0.0..=4.0).text("Sharpen"))
+                .changed();
+            if adj.sharpen.amount > 0.0 {
+                changed |= ui
+                    .add(egui::Slider::new(&mut adj.sharpen.radius, 0.3..=8.0).text("Radius"))
+                    .changed();
+                changed |= ui
+                    .add(egui::Slider::new(&mut adj.sharpen.threshold, 0..=64).text("Threshold"))
+                    .changed();
+            }
+            changed |= ui
+                .add(
+                    egui::Slider::new(&mut adj.gamma, 0.1..=4.0)
+                        .logarithmic(true)
+                        .text("Gamma"),
+                )
+                .changed();
+            ui.horizontal(|ui| {
+                changed |= ui
+                    .add(
+                        egui::DragValue::new(&mut adj.black_point)
+                            .range(0..=254)
+                            .prefix("Black: "),
+                    )
+                    .changed();
+                changed |= ui
+                    .add(
+                        egui::DragValue::new(&mut adj.white_point)
+                            .range(1..=255)
+                            .prefix("White: "),
+                    )
+                    .changed();
+            });
+
+            ui.collapsing("Curve", |ui| {
+                let mut remove = None;
+                for (i, (input, output)) in adj.curve.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        changed |= ui.add(egui::DragValue::new(input).prefix("In: ")).changed();
+                        changed |= ui
+                            .add(egui::DragValue::new(output).prefix("Out: "))
+                            .changed();
+                        if ui.small_button("✖").clicked() {
+                            remove = Some(i);
+                        }
+                    });
+                }
+                if let Some(i) = remove {
+                    adj.curve.remove(i);
                     changed = true;
                 }
+                ui.horizontal(|ui| {
+                    if ui.button("➕ Point").clicked() {
+                        if adj.curve.is_empty() {
+                            adj.curve.extend([(0, 0), (255, 255)]);
+                        }
+                        adj.curve.push((128, 128));
+                        changed = true;
+                    }
+                    if ui.button("Clear").clicked() && !adj.curve.is_empty() {
+                        adj.curve.clear();
+                        changed = true;
+                    }
+                });
+            });
+
+            if ui.button("Reset").clicked() {
+                *adj = dither_core::Adjustments::default();
+                changed = true;
             }
         });
         changed
@@ -194,36 +543,143 @@ impl MyApp {
         ui.group(|ui| {
             ui.label("Algorithm");
             egui::ComboBox::from_id_salt("algo")
-                .selected_text(format!("{:?}", self.selected_algorythm))
+                .selected_text(format!("{:?}", self.settings.algorithm))
                 .show_ui(ui, |ui| {
                     changed |= ui
                         .selectable_value(
-                            &mut self.selected_algorythm,
-                            DitherAlgorythm::Original,
+                            &mut self.settings.algorithm,
+                            Algorithm::Original,
                             "Original",
                         )
                         .changed();
+                    changed |= ui
+                        .selectable_value(&mut self.settings.algorithm, Algorithm::Bayer, "Bayer")
+                        .changed();
+                    changed |= ui
+                        .selectable_value(
+                            &mut self.settings.algorithm,
+                            Algorithm::WhiteNoise,
+                            "White Noise",
+                        )
+                        .changed();
+                    changed |= ui
+                        .selectable_value(
+                            &mut self.settings.algorithm,
+                            Algorithm::TriangularNoise,
+                            "Triangular Noise",
+                        )
+                        .changed();
                     changed |= ui
                         .selectable_value(
-                            &mut self.selected_algorythm,
-                            DitherAlgorythm::Bayer,
-                            "Bayer",
+                            &mut self.settings.algorithm,
+                            Algorithm::InterleavedGradient,
+                            "Interleaved Gradient",
                         )
                         .changed();
                     changed |= ui
                         .selectable_value(
-                            &mut self.selected_algorythm,
-                            DitherAlgorythm::Floyd,
-                            "Floyd",
+                            &mut self.settings.algorithm,
+                            Algorithm::CustomMap,
+                            "Custom Map",
+                        )
+                        .changed();
+                    changed |= ui
+                        .selectable_value(&mut self.settings.algorithm, Algorithm::Floyd, "Floyd")
+                        .changed();
+                    changed |= ui
+                        .selectable_value(&mut self.settings.algorithm, Algorithm::Hybrid, "Hybrid")
+                        .changed();
+                    changed |= ui
+                        .selectable_value(
+                            &mut self.settings.algorithm,
+                            Algorithm::Binarize,
+                            "Binarize",
                         )
                         .changed();
                 });
 
-            if self.selected_algorythm == DitherAlgorythm::Bayer {
-                let label = format!("Matrix: {}", 2_usize.pow(self.dither_bayer_size as u32));
+            if self.settings.algorithm == Algorithm::Binarize {
+                changed |= ui_binarization(ui, &mut self.settings.binarization);
+            }
+
+            if self.settings.algorithm == Algorithm::Hybrid {
+                changed |= ui
+                    .add(
+                        egui::Slider::new(&mut self.settings.hybrid_blend, 0.0..=1.0)
+                            .text("Ordered ↔ Diffusion"),
+                    )
+                    .changed();
+                ui.add_enabled_ui(self.settings.custom_map.is_some(), |ui| {
+                    changed |= ui
+                        .checkbox(&mut self.settings.hybrid_custom_map, "Use custom map")
+                        .changed();
+                });
+            }
+
+            let uses_bayer_matrix = self.settings.algorithm == Algorithm::Bayer
+                || (self.settings.algorithm == Algorithm::Hybrid
+                    && !(self.settings.hybrid_custom_map && self.settings.custom_map.is_some()));
+
+            if uses_bayer_matrix {
+                let label = format!("Matrix: {}", 2_usize.pow(self.settings.bayer_size as u32));
+                changed |= ui
+                    .add(egui::Slider::new(&mut self.settings.bayer_size, 1..=6).text(label))
+                    .changed();
+            }
+
+            if self.settings.algorithm.is_diffusion() {
                 changed |= ui
-                    .add(egui::Slider::new(&mut self.dither_bayer_size, 1..=6).text(label))
+                    .add(
+                        egui::Slider::new(&mut self.settings.diffusion.strength, 0.0..=1.0)
+                            .custom_formatter(|v, _| format!("{:.0}%", v * 100.0))
+                            .text("Diffusion"),
+                    )
                     .changed();
+                changed |= ui
+                    .add(
+                        egui::Slider::new(&mut self.settings.diffusion.bias, -127..=127)
+                            .text("Bias"),
+                    )
+                    .changed();
+                changed |= ui
+                    .add(
+                        egui::Slider::new(&mut self.settings.diffusion.noise, 0..=127)
+                            .text("Noise"),
+                    )
+                    .changed();
+            }
+
+            let uses_seed = matches!(
+                self.settings.algorithm,
+                Algorithm::WhiteNoise | Algorithm::TriangularNoise | Algorithm::InterleavedGradient
+            ) || (self.settings.algorithm.is_diffusion()
+                && self.settings.diffusion.noise > 0);
+
+            if uses_seed {
+                ui.horizontal(|ui| {
+                    changed |= ui
+                        .add(egui::DragValue::new(&mut self.settings.noise_seed).prefix("Seed: "))
+                        .changed();
+                    if ui.button("🎲").clicked() {
+                        self.settings.noise_seed = self.settings.noise_seed.wrapping_add(1);
+                        changed = true;
+                    }
+                });
+            }
+
+            if self.settings.algorithm == Algorithm::CustomMap
+                || self.settings.algorithm == Algorithm::Hybrid
+            {
+                ui.horizontal(|ui| {
+                    if ui.button("📂 Load Map").clicked() {
+                        changed |= self.load_custom_map();
+                    }
+                    if self.custom_map_name.is_empty() {
+                        ui.label("No map loaded");
+                    } else {
+                        ui.label(&self.custom_map_name);
+                    }
+                });
             }
         });
         changed
@@ -235,27 +691,288 @@ impl MyApp {
             ui.label("Color Mode");
             ui.horizontal(|ui| {
                 changed |= ui
-                    .selectable_value(&mut self.selected_mode, DitherMode::Grayscale, "Gray")
+                    .selectable_value(&mut self.settings.mode, DitherMode::Grayscale, "Gray")
                     .changed();
                 changed |= ui
-                    .selectable_value(&mut self.selected_mode, DitherMode::Colored, "RGB")
+                    .selectable_value(&mut self.settings.mode, DitherMode::Colored, "RGB")
                     .changed();
                 changed |= ui
-                    .selectable_value(&mut self.selected_mode, DitherMode::Duoton, "2-Bit")
+                    .selectable_value(&mut self.settings.mode, DitherMode::Duoton, "Tones")
                     .changed();
             });
 
-            if self.selected_mode == DitherMode::Duoton {
+            if self.settings.mode != DitherMode::Colored {
+                changed |= self.ui_luma_model(ui);
+            }
+
+            if self.settings.mode == DitherMode::Colored {
+                use dither_core::DitherSpace;
+
+                egui::ComboBox::from_label("Space")
+                    .selected_text(format!("{:?}", self.settings.dither_space))
+                    .show_ui(ui, |ui| {
+                        for (space, name) in [
+                            (DitherSpace::Rgb, "RGB"),
+                            (DitherSpace::YCbCr, "YCbCr (luma only)"),
+                            (DitherSpace::OkLab, "OKLab (luma only)"),
+                            (DitherSpace::Cmy, "CMY"),
+                        ] {
+                            changed |= ui
+                                .selectable_value(&mut self.settings.dither_space, space, name)
+                                .changed();
+                        }
+                    });
+            }
+
+            if self.settings.mode == DitherMode::Colored
+                && self.settings.algorithm.is_ordered()
+                && !self.settings.dither_space.is_luma_only()
+            {
+                use dither_core::ChannelDecorrelation;
+
+                egui::ComboBox::from_label("Channels")
+                    .selected_text(format!("{:?}", self.settings.channel_decorrelation))
+                    .show_ui(ui, |ui| {
+                        for (mode, name) in [
+                            (ChannelDecorrelation::None, "Shared"),
+                            (ChannelDecorrelation::Offset, "Offset"),
+                            (ChannelDecorrelation::Rotate, "Rotate"),
+                            (ChannelDecorrelation::Transpose, "Transpose"),
+                        ] {
+                            changed |= ui
+                                .selectable_value(
+                                    &mut self.settings.channel_decorrelation,
+                                    mode,
+                                    name,
+                                )
+                                .changed();
+                        }
+                    });
+            }
+
+            if self.settings.mode == DitherMode::Duoton {
                 ui.separator();
-                ui.horizontal(|ui| {
-                    changed |= ui.color_edit_button_srgb(&mut self.color_low).changed();
-                    if ui.button("<->").clicked() {
-                        std::mem::swap(&mut self.color_low, &mut self.color_high);
-                        changed = true;
-                    }
-                    changed |= ui.color_edit_button_srgb(&mut self.color_high).changed();
+                changed |= self.ui_color_stops(ui);
+            }
+        });
+        changed
+    }
+
+    fn ui_edge_section(&mut self, ui: &mut egui::Ui) -> bool {
+        use dither_core::EdgeMode;
+
+        let mut changed = false;
+        ui.group(|ui| {
+            ui.label("Edges");
+            ui.horizontal(|ui| {
+                for (mode, name) in [
+                    (EdgeMode::Off, "Off"),
+                    (EdgeMode::Modulate, "Modulate"),
+                    (EdgeMode::Hard, "Hard"),
+                ] {
+                    changed |= ui
+                        .selectable_value(&mut self.settings.edge.mode, mode, name)
+                        .changed();
+                }
+            });
+
+            match self.settings.edge.mode {
+                EdgeMode::Off => {}
+                EdgeMode::Modulate => {
+                    changed |= ui
+                        .add(
+                            egui::Slider::new(&mut self.settings.edge.strength, 0.0..=8.0)
+                                .text("Strength"),
+                        )
+                        .changed();
+                }
+                EdgeMode::Hard => {
+                    changed |= ui
+                        .add(
+                            egui::Slider::new(&mut self.settings.edge.threshold, 1..=255)
+                                .text("Threshold"),
+                        )
+                        .changed();
+                }
+            }
+        });
+        changed
+    }
+
+    fn ui_luma_model(&mut self, ui: &mut egui::Ui) -> bool {
+        use dither_core::LumaModel;
+
+        let mut changed = false;
+        let label = match self.settings.luma_model {
+            LumaModel::Custom(_) => "Custom".to_string(),
+            model => format!("{model:?}"),
+        };
+        egui::ComboBox::from_label("Luminance")
+            .selected_text(label)
+            .show_ui(ui, |ui| {
+                for (model, name) in [
+                    (LumaModel::Rec601, "Rec.601"),
+                    (LumaModel::Rec709, "Rec.709"),
+                    (LumaModel::LinearLight, "Linear light"),
+                    (LumaModel::Red, "Red channel"),
+                    (LumaModel::Green, "Green channel"),
+                    (LumaModel::Blue, "Blue channel"),
+                ] {
+                    changed |= ui
+                        .selectable_value(&mut self.settings.luma_model, model, name)
+                        .changed();
+                }
+                if ui
+                    .selectable_label(
+                        matches!(self.settings.luma_model, LumaModel::Custom(_)),
+                        "Custom",
+                    )
+                    .clicked()
+                    && !matches!(self.settings.luma_model, LumaModel::Custom(_))
+                {
+                    self.settings.luma_model = LumaModel::Custom([1.0, 1.0, 1.0]);
+                    changed = true;
+                }
+            });
+
+        if let LumaModel::Custom(weights) = &mut self.settings.luma_model {
+            ui.horizontal(|ui| {
+                for (w, prefix) in weights.iter_mut().zip(["R: ", "G: ", "B: "]) {
+                    changed |= ui
+                        .add(
+                            egui::DragValue::new(w)
+                                .speed(0.01)
+                                .range(0.0..=10.0)
+                                .prefix(prefix),
+                        )
+                        .changed();
+                }
+            });
+        }
+        changed
+    }
+
+    fn ui_color_stops(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+        let mut remove = None;
+        let removable = self.settings.color_stops.len() > 2;
+
+        for (i, stop) in self.settings.color_stops.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                changed |= ui.color_edit_button_srgb(&mut stop.color).changed();
+                changed |= ui
+                    .add(egui::Slider::new(&mut stop.position, 0..=255).show_value(true))
+                    .changed();
+                if removable && ui.small_button("✖").clicked() {
+                    remove = Some(i);
+                }
+            });
+        }
+
+        if let Some(i) = remove {
+            self.settings.color_stops.remove(i);
+            changed = true;
+        }
+
+        ui.horizontal(|ui| {
+            if ui.button("➕ Stop").clicked() {
+                self.add_color_stop();
+                changed = true;
+            }
+            if ui.button("<->").clicked() {
+                self.settings.color_stops.sort_by_key(|s| s.position);
+                let colors: Vec<[u8; 3]> = self
+                    .settings
+                    .color_stops
+                    .iter()
+                    .rev()
+                    .map(|s| s.color)
+                    .collect();
+                for (stop, color) in self.settings.color_stops.iter_mut().zip(colors) {
+                    stop.color = color;
+                }
+                changed = true;
+            }
+        });
+
+        changed
+    }
+
+    // Nowy przystanek w srodku najwiekszej przerwy, z kolorem posrodku sasiadow
+    fn add_color_stop(&mut self) {
+        self.settings.color_stops.sort_by_key(|s| s.position);
+        let Some(i) = (1..self.settings.color_stops.len()).max_by_key(|&i| {
+            self.settings.color_stops[i].position - self.settings.color_stops[i - 1].position
+        }) else {
+            return;
+        };
+
+        let (a, b) = (
+            self.settings.color_stops[i - 1],
+            self.settings.color_stops[i],
+        );
+        let position = ((a.position as u16 + b.position as u16) / 2) as u8;
+        let color: [u8; 3] =
+            std::array::from_fn(|c| ((a.color[c] as u16 + b.color[c] as u16) / 2) as u8);
+        self.settings
+            .color_stops
+            .insert(i, dither_core::ColorStop::new(position, color));
+    }
+
+    fn ui_crop(&mut self, ui: &mut egui::Ui) -> bool {
+        let Some(img) = &self.original_image else {
+            return false;
+        };
+        let (img_w, img_h) = (img.width(), img.height());
+        let mut changed = false;
+        self.clamp_crop();
+
+        ui.collapsing("Crop", |ui| {
+            let mut enabled = self.settings.crop.is_some();
+            if ui.checkbox(&mut enabled, "Enabled").changed() {
+                self.settings.crop = enabled.then_some(dither_core::Crop {
+                    x: 0,
+                    y: 0,
+                    width: img_w,
+                    height: img_h,
                 });
+                changed = true;
             }
+            let Some(crop) = &mut self.settings.crop else {
+                return;
+            };
+            ui.horizontal(|ui| {
+                changed |= ui
+                    .add(
+                        egui::DragValue::new(&mut crop.x)
+                            .range(0..=img_w.saturating_sub(1))
+                            .prefix("X: "),
+                    )
+                    .changed();
+                changed |= ui
+                    .add(
+                        egui::DragValue::new(&mut crop.y)
+                            .range(0..=img_h.saturating_sub(1))
+                            .prefix("Y: "),
+                    )
+                    .changed();
+            });
+            ui.horizontal(|ui| {
+                changed |= ui
+                    .add(
+                        egui::DragValue::new(&mut crop.width)
+                            .range(1..=img_w.saturating_sub(crop.x).max(1))
+                            .prefix("W: "),
+                    )
+                    .changed();
+                changed |= ui
+                    .add(
+                        egui::DragValue::new(&mut crop.height)
+                            .range(1..=img_h.saturating_sub(crop.y).max(1))
+                            .prefix("H: "),
+                    )
+                    .changed();
+            });
         });
         changed
     }
@@ -268,11 +985,14 @@ impl MyApp {
             return;
         }
 
-        let aspect = orig.width() as f32 / orig.height() as f32;
+        let (width, height) = self.settings.cropped_size(orig.width(), orig.height());
+        let aspect = width as f32 / height as f32;
         if width_changed {
-            self.target_height = (self.target_width as f32 / aspect).round() as u32;
+            self.settings.target_height =
+                (self.settings.target_width as f32 / aspect).round() as u32;
         } else if height_changed {
-            self.target_width = (self.target_height as f32 * aspect).round() as u32;
+            self.settings.target_width =
+                (self.settings.target_height as f32 * aspect).round() as u32;
         }
     }
 }
@@ -290,22 +1010,20 @@ impl eframe::App for MyApp {
         egui::SidePanel::right("controls").show(ctx, |ui| {
             ui.vertical_centered(|ui| {
                 ui.add_space(10.0);
-                self.ui_file_section(ui);
+                if self.ui_file_section(ui) {
+                    self.apply_effect();
+                }
 
                 ui.add_enabled_ui(self.original_image.is_some(), |ui| {
                     let mut needs_update = false;
 
                     needs_update |= self.ui_resize_section(ui);
 
-                    ui.group(|ui| {
-                        ui.label("Contrast");
-                        needs_update |= ui
-                            .add(egui::Slider::new(&mut self.contrast, -50.0..=50.0))
-                            .changed();
-                    });
+                    needs_update |= self.ui_adjustments_section(ui);
 
                     needs_update |= self.ui_algorithm_section(ui);
                     needs_update |= self.ui_color_section(ui);
+                    needs_update |= self.ui_edge_section(ui);
 
                     if needs_update {
                         self.apply_effect();
@@ -330,10 +1048,102 @@ impl eframe::App for MyApp {
         egui::CentralPanel::default().show(ctx, |ui| {
             self.render_viewport(ui, ctx);
         });
+
+        self.ui_device_export(ctx);
     }
 }
 
 impl MyApp {
+    fn ui_device_export(&mut self, ctx: &egui::Context) {
+        use dither_core::{BitOrder, DeviceFormat, Layout, Planes};
+
+        let mut open = self.show_device_export;
+        let mut export = false;
+        egui::Window::new("Export for device")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let opts = &mut self.device_options;
+                egui::ComboBox::from_label("Format")
+                    .selected_text(format!("{:?}", self.device_format))
+                    .show_ui(ui, |ui| {
+                        for (format, name) in DeviceFormat::ALL.into_iter().zip([
+                            "Raw binary",
+                            "C header (PROGMEM)",
+                            "Rust const",
+                            "MicroPython bytes",
+                            "XBM (Adafruit GFX / u8g2)",
+                        ]) {
+                            ui.selectable_value(&mut self.device_format, format, name);
+                        }
+                    });
+                if self.device_format != DeviceFormat::Raw {
+                    ui.horizontal(|ui| {
+                        ui.label("Name:");
+                        ui.text_edit_singleline(&mut self.device_name);
+                    });
+                }
+
+                // XBM ma staly uklad bitow
+                ui.add_enabled_ui(self.device_format != DeviceFormat::Xbm, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.selectable_value(&mut opts.planes, Planes::Single, "Single");
+                        ui.selectable_value(&mut opts.planes, Planes::BlackRed, "Black + Red");
+                    });
+                    ui.add_enabled_ui(opts.planes == Planes::Single, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label("Bits:");
+                            for bits in [1, 2, 4, 8] {
+                                ui.selectable_value(&mut opts.bits, bits, bits.to_string());
+                            }
+                        });
+                    });
+                    ui.horizontal(|ui| {
+                        ui.selectable_value(&mut opts.layout, Layout::Rows, "Rows");
+                        ui.selectable_value(&mut opts.layout, Layout::Pages, "Pages (SSD1306)");
+                    });
+                    ui.horizontal(|ui| {
+                        ui.selectable_value(&mut opts.bit_order, BitOrder::MsbFirst, "MSB first");
+                        ui.selectable_value(&mut opts.bit_order, BitOrder::LsbFirst, "LSB first");
+                    });
+                    ui.add(
+                        egui::DragValue::new(&mut opts.row_align)
+                            .range(1..=64)
+                            .prefix("Row align: ")
+                            .suffix(" B"),
+                    );
+                });
+                ui.checkbox(&mut opts.invert, "Invert");
+
+                if let Some(img) = &self.raw_image {
+                    let effective = self.device_format.pack_options(opts);
+                    let planes = match effective.planes {
+                        Planes::Single => 1,
+                        Planes::BlackRed => 2,
+                    };
+                    if let Ok(size) = effective.plane_size(img.width(), img.height()) {
+                        ui.label(format!(
+                            "{}x{}, {} bytes",
+                            img.width(),
+                            img.height(),
+                            size * planes
+                        ));
+                    }
+                }
+
+                if ui.button("💾 Export…").clicked() {
+                    export = true;
+                }
+                if !self.device_status.is_empty() {
+                    ui.label(&self.device_status);
+                }
+            });
+        self.show_device_export = open;
+        if export {
+            self.export_for_device();
+        }
+    }
+
     fn render_viewport(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
         if let Some(raw_img) = &self.raw_image {
             let texture = self.texture.get_or_insert_with(|| {
@@ -345,13 +1155,16 @@ impl MyApp {
                 ctx.load_texture("img", color_image, egui::TextureOptions::NEAREST)
             });
 
-            egui::ScrollArea::both()
-                .auto_shrink([false; 2])
-                .show(ui, |ui| {
-                    ui.centered_and_justified(|ui| {
-                        ui.image((texture.id(), texture.size_vec2() * self.zoom_factor));
-                    });
+            let mut scroll = egui::ScrollArea::both().auto_shrink([false; 2]);
+            if let Some(offset) = self.restore_scroll.take() {
+                scroll = scroll.scroll_offset(offset);
+            }
+            let output = scroll.show(ui, |ui| {
+                ui.centered_and_justified(|ui| {
+                    ui.image((texture.id(), texture.size_vec2() * self.zoom_factor));
                 });
+            });
+            self.scroll_offset = output.state.offset;
         } else {
             ui.centered_and_justified(|ui| {
                 ui.heading("Load an image to start");
@@ -359,3 +1172,218 @@ impl MyApp {
         }
     }
 }
+
+fn ui_binarization(ui: &mut egui::Ui, method: &mut dither_core::Binarization) -> bool {
+    use dither_core::Binarization;
+
+    let mut changed = false;
+    let label = match method {
+        Binarization::Otsu => "Otsu",
+        Binarization::Sauvola { .. } => "Sauvola",
+        Binarization::Niblack { .. } => "Niblack",
+        Binarization::Bradley { .. } => "Bradley",
+    };
+    egui::ComboBox::from_label("Method")
+        .selected_text(label)
+        .show_ui(ui, |ui| {
+            for (option, name) in [
+                (Binarization::Otsu, "Otsu"),
+                (
+                    Binarization::Sauvola {
+                        radius: 15,
+                        k: 0.34,
+                    },
+                    "Sauvola",
+                ),
+                (
+                    Binarization::Niblack {
+                        radius: 15,
+                        k: -0.2,
+                    },
+                    "Niblack",
+                ),
+                (
+                    Binarization::Bradley {
+                        radius: 15,
+                        t: 0.15,
+                    },
+                    "Bradley",
+                ),
+            ] {
+                let selected = std::mem::discriminant(method) == std::mem::discriminant(&option);
+                if ui.selectable_label(selected, name).clicked() && !selected {
+                    *method = option;
+                    changed = true;
+                }
+            }
+        });
+
+    match method {
+        Binarization::Otsu => {}
+        Binarization::Sauvola { radius, k } => {
+            changed |= ui
+                .add(egui::Slider::new(radius, 1..=64).text("Window radius"))
+                .changed();
+            changed |= ui.add(egui::Slider::new(k, 0.0..=1.0).text("k")).changed();
+        }
+        Binarization::Niblack { radius, k } => {
+            changed |= ui
+                .add(egui::Slider::new(radius, 1..=64).text("Window radius"))
+                .changed();
+            changed |= ui.add(egui::Slider::new(k, -1.0..=1.0).text("k")).changed();
+        }
+        Binarization::Bradley { radius, t } => {
+            changed |= ui
+                .add(egui::Slider::new(radius, 1..=64).text("Window radius"))
+                .changed();
+            changed |= ui
+                .add(
+                    egui::Slider::new(t, 0.0..=0.5)
+                        .custom_formatter(|v, _| format!("{:.0}%", v * 100.0))
+                        .text("Sensitivity"),
+                )
+                .changed();
+        }
+    }
+    changed
+}
+
+fn ui_denoise(ui: &mut egui::Ui, denoise: &mut dither_core::Denoise) -> bool {
+    use dither_core::Denoise;
+
+    let mut changed = false;
+    let label = match denoise {
+        Denoise::None => "None",
+        Denoise::Median { .. } => "Median",
+        Denoise::Bilateral { .. } => "Bilateral",
+    };
+    egui::ComboBox::from_label("Denoise")
+        .selected_text(label)
+        .show_ui(ui, |ui| {
+            for (option, name) in [
+                (Denoise::None, "None"),
+                (Denoise::Median { radius: 1 }, "Median"),
+                (
+                    Denoise::Bilateral {
+                        radius: 2,
+                        sigma_color: 25.0,
+                    },
+                    "Bilateral",
+                ),
+            ] {
+                let selected = std::mem::discriminant(denoise) == std::mem::discriminant(&option);
+                if ui.selectable_label(selected, name).clicked() && !selected {
+                    *denoise = option;
+                    changed = true;
+                }
+            }
+        });
+
+    match denoise {
+        Denoise::Median { radius } => {
+            changed |= ui
+                .add(egui::Slider::new(radius, 1..=5).text("Denoise radius"))
+                .changed();
+        }
+        Denoise::Bilateral {
+            radius,
+            sigma_color,
+        } => {
+            changed |= ui
+                .add(egui::Slider::new(radius, 1..=5).text("Denoise radius"))
+                .changed();
+            changed |= ui
+                .add(egui::Slider::new(sigma_color, 1.0..=100.0).text("Strength"))
+                .changed();
+        }
+        Denoise::None => {}
+    }
+    changed
+}
+
+fn ui_equalization(ui: &mut egui::Ui, eq: &mut dither_core::Equalization) -> bool {
+    use dither_core::Equalization;
+
+    let mut changed = false;
+    let label = match eq {
+        Equalization::None => "None",
+        Equalization::Global => "Equalize",
+        Equalization::Clahe { .. } => "CLAHE",
+        Equalization::AutoLevels { .. } => "Auto levels",
+    };
+    egui::ComboBox::from_label("Histogram")
+        .selected_text(label)
+        .show_ui(ui, |ui| {
+            for (option, name) in [
+                (Equalization::None, "None"),
+                (Equalization::Global, "Equalize"),
+                (
+                    Equalization::Clahe {
+                        tiles: 8,
+                        clip_limit: 2.0,
+                    },
+                    "CLAHE",
+                ),
+                (Equalization::AutoLevels { clip: 0.5 }, "Auto levels"),
+            ] {
+                let selected = std::mem::discriminant(eq) == std::mem::discriminant(&option);
+                if ui.selectable_label(selected, name).clicked() && !selected {
+                    *eq = option;
+                    changed = true;
+                }
+            }
+        });
+
+    match eq {
+        Equalization::Clahe { tiles, clip_limit } => {
+            changed |= ui
+                .add(egui::Slider::new(tiles, 1..=32).text("Tiles"))
+                .changed();
+            changed |= ui
+                .add(egui::Slider::new(clip_limit, 1.0..=16.0).text("Clip limit"))
+                .changed();
+        }
+        Equalization::AutoLevels { clip } => {
+            changed |= ui
+                .add(egui::Slider::new(clip, 0.0..=10.0).text("Clip %"))
+                .changed();
+        }
+        Equalization::None | Equalization::Global => {}
+    }
+    changed
+}
+
+fn ui_histogram(ui: &mut egui::Ui, before: &[u32; 256], after: &[u32; 256]) {
+    let (rect, _) =
+        ui.allocate_exact_size(egui::vec2(ui.available_width(), 80.0), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+
+    let max = before
+        .iter()
+        .chain(after)
+        .copied()
+        .max()
+        .unwrap_or(1)
+        .max(1) as f32;
+    let line = |hist: &[u32; 256]| -> Vec<egui::Pos2> {
+        hist.iter()
+            .enumerate()
+            .map(|(i, &c)| {
+                egui::pos2(
+                    rect.left() + rect.width() * i as f32 / 255.0,
+                    rect.bottom() - rect.height() * (c as f32 / max).sqrt(),
+                )
+            })
+            .collect()
+    };
+
+    painter.add(egui::Shape::line(
+        line(before),
+        egui::Stroke::new(1.0, egui::Color32::GRAY),
+    ));
+    painter.add(egui::Shape::line(
+        line(after),
+        egui::Stroke::new(1.0, egui::Color32::LIGHT_BLUE),
+    ));
+}